@@ -11,7 +11,7 @@ impl _Cell {
         matches!(self, Self::Filled(..))
     }
     pub fn bit(&self) -> u16 {
-        return self.into();
+        self.into()
     }
     pub fn to_determined_number(&self) -> Option<u8> {
         if self.bit().count_ones() == 1 {
@@ -24,7 +24,7 @@ impl _Cell {
         if self.is_filled() {
             return false;
         }
-        self.to_determined_number().and_then(|i| {*self = Self::Filled(i); Some(())}).is_some()
+        self.to_determined_number().map(|i| *self = Self::Filled(i)).is_some()
     }
 
     pub fn insert(&mut self, value: u8) -> bool {
@@ -37,30 +37,24 @@ impl _Cell {
     }
 }
 
-impl Into<u16> for _Cell {
-    fn into(self) -> u16 {
-        match self {
-            Self::Filled(i) => 1 << (i - 1),
-            Self::Unfilled(b) => b,
-        }
+impl From<_Cell> for u16 {
+    fn from(value: _Cell) -> Self {
+        (&value).into()
     }
 }
 
-impl Into<u16> for &_Cell {
-    fn into(self) -> u16 {
-        match self {
-            &_Cell::Filled(i) => 1 << (i - 1),
-            &_Cell::Unfilled(b) => b,
+impl From<&_Cell> for u16 {
+    fn from(value: &_Cell) -> Self {
+        match *value {
+            _Cell::Filled(i) => 1 << (i - 1),
+            _Cell::Unfilled(b) => b,
         }
     }
 }
 
-impl Into<u16> for &mut _Cell {
-    fn into(self) -> u16 {
-        match self {
-            &mut _Cell::Filled(i) => 1 << (i - 1),
-            &mut _Cell::Unfilled(b) => b,
-        }
+impl From<&mut _Cell> for u16 {
+    fn from(value: &mut _Cell) -> Self {
+        (&*value).into()
     }
 }
 
@@ -70,7 +64,7 @@ where
 {
     fn bitand_assign(&mut self, rhs: T) {
         match self {
-            Self::Filled(..) => return,
+            Self::Filled(..) => {}
             Self::Unfilled(b) => *self = Self::Unfilled(*b & rhs.into()),
         }
     }
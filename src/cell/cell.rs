@@ -58,6 +58,10 @@ impl Cell {
         self.pos.block_idx()
     }
 
+    pub fn to_determined_number(&self) -> Option<u8> {
+        self.cell.to_determined_number()
+    }
+
     pub fn determine(&mut self) -> bool {
         self.cell.determine()
     }
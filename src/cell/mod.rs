@@ -1,3 +1,4 @@
 mod _cell;
+#[allow(clippy::module_inception)]
 mod cell;
 pub use cell::Cell;
\ No newline at end of file
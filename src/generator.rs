@@ -0,0 +1,284 @@
+use std::{fmt::Display, ops::RangeBounds};
+
+use crate::{
+    puzzle::{PuzzleIndex, TechniqueKind},
+    rating::{rate, Rating, Tier},
+    Puzzle,
+};
+
+// 再現性のためのシード付き乱数 (splitmix64)
+#[derive(Debug, Clone)]
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    // 0..nの一様乱数
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+
+    pub fn shuffle<T>(&mut self, v: &mut [T]) {
+        for i in (1..v.len()).rev() {
+            let j = self.below(i + 1);
+            v.swap(i, j);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Symmetry {
+    None,
+    // 中心について180度回転
+    Rotational,
+    // 左右対称
+    Mirror,
+    // 対角線について対称
+    Diagonal,
+}
+
+impl Symmetry {
+    // マス (col * 9 + row) と同時に消すべきマスの集合
+    fn orbit(&self, i: usize) -> Vec<usize> {
+        let (col, row) = (i / 9, i % 9);
+        let other = match self {
+            Self::None => i,
+            Self::Rotational => (8 - col) * 9 + (8 - row),
+            Self::Mirror => col * 9 + (8 - row),
+            Self::Diagonal => row * 9 + col,
+        };
+        if other == i {
+            vec![i]
+        } else {
+            vec![i, other]
+        }
+    }
+}
+
+// 生成された問題. マス (col * 9 + row) ごとの数字で持ち, 0はヒントなし
+#[derive(Debug, Clone)]
+pub struct Generated {
+    clues: [u8; 81],
+    solution: [u8; 81],
+}
+
+impl Generated {
+    pub fn clues(&self) -> &[u8; 81] {
+        &self.clues
+    }
+
+    pub fn solution(&self) -> &[u8; 81] {
+        &self.solution
+    }
+
+    pub fn clue_count(&self) -> usize {
+        self.clues.iter().filter(|&&v| v != 0).count()
+    }
+
+    // ヒントだけを入れた盤面. 連鎖して決まるマスは埋めないので, 評価ではNaked Singleとして数えられる
+    pub fn puzzle(&self) -> Puzzle {
+        Puzzle::from_clues(&self.clues)
+    }
+
+    // どのヒントを一つ消しても解が一つに定まらなくなるか
+    pub fn is_minimal(&self) -> bool {
+        (0..81).filter(|&i| self.clues[i] != 0).all(|i| {
+            let mut clues = self.clues;
+            clues[i] = 0;
+            Puzzle::from_clues(&clues).count_solutions(2) > 1
+        })
+    }
+}
+
+// main.rsが読み込める形式 ('*'が空きマス) で出力する
+impl Display for Generated {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for v in self.clues {
+            if v == 0 {
+                write!(f, "*")?;
+            } else {
+                write!(f, "{}", v)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+pub struct Generator {
+    rng: Rng,
+    symmetry: Symmetry,
+    target_clues: Option<usize>,
+}
+
+impl Generator {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            rng: Rng::new(seed),
+            symmetry: Symmetry::None,
+            target_clues: None,
+        }
+    }
+
+    pub fn symmetry(mut self, symmetry: Symmetry) -> Self {
+        self.symmetry = symmetry;
+        self
+    }
+
+    // ヒントがこの数になったら消すのをやめ, これより少なくなる消し方はしない. 指定しなければ消せなくなるまで消す
+    pub fn target_clues(mut self, target_clues: usize) -> Self {
+        self.target_clues = Some(target_clues);
+        self
+    }
+
+    // target_cluesで途中で止めない限り, symmetryを保ったまま消せるヒントは残らない.
+    // Symmetry::Noneならば結果は最小 (is_minimal) となる.
+    pub fn generate(&mut self) -> Generated {
+        let solution = self.random_grid();
+        let mut clues = solution;
+        let mut order = (0..81).collect::<Vec<_>>();
+        self.rng.shuffle(&mut order);
+        for i in order {
+            if self
+                .target_clues
+                .is_some_and(|t| clues.iter().filter(|&&v| v != 0).count() <= t)
+            {
+                break;
+            }
+            if clues[i] == 0 {
+                continue;
+            }
+            let mut removed = clues;
+            for j in self.symmetry.orbit(i) {
+                removed[j] = 0;
+            }
+            // 組ごと消すとtarget_cluesを下回るなら, この組は残す
+            if self
+                .target_clues
+                .is_some_and(|t| removed.iter().filter(|&&v| v != 0).count() < t)
+            {
+                continue;
+            }
+            if Puzzle::from_clues(&removed).count_solutions(2) == 1 {
+                clues = removed;
+            }
+        }
+        Generated { clues, solution }
+    }
+
+    // acceptを満たす評価の問題ができるまで最大max_attempts回生成し直す.
+    pub fn generate_matching(
+        &mut self,
+        accept: impl Fn(&Rating) -> bool,
+        max_attempts: usize,
+    ) -> Option<Generated> {
+        (0..max_attempts)
+            .map(|_| self.generate())
+            .find(|generated| rate(&generated.puzzle()).is_ok_and(|r| accept(&r)))
+    }
+
+    // techniqueが必要で, それより難しい解法は必要ない問題
+    pub fn generate_requiring(
+        &mut self,
+        technique: TechniqueKind,
+        max_attempts: usize,
+    ) -> Option<Generated> {
+        self.generate_matching(
+            |rating| rating.tier() != Tier::BeyondSolver && rating.hardest() == Some(technique),
+            max_attempts,
+        )
+    }
+
+    // 点数がbandに収まり, 論理的な解法だけで解ける問題
+    pub fn generate_in_band(
+        &mut self,
+        band: impl RangeBounds<u32>,
+        max_attempts: usize,
+    ) -> Option<Generated> {
+        self.generate_matching(
+            |rating| rating.tier() != Tier::BeyondSolver && band.contains(&rating.score()),
+            max_attempts,
+        )
+    }
+
+    fn random_grid(&mut self) -> [u8; 81] {
+        // 対角線上の三つのブロックは互いに影響しないので, ランダムに埋めてから残りを探索で埋める.
+        let mut puzzle = Puzzle::new();
+        for block in [0, 4, 8] {
+            let mut digits = (1..=9).collect::<Vec<u8>>();
+            self.rng.shuffle(&mut digits);
+            for (k, v) in digits.into_iter().enumerate() {
+                let col = block / 3 * 3 + k / 3;
+                let row = block % 3 * 3 + k % 3;
+                let _ = puzzle.fill(PuzzleIndex::new(col, row), v);
+            }
+        }
+        puzzle.solve_by_backtracking();
+        let mut ret = [0; 81];
+        for (i, v) in ret.iter_mut().enumerate() {
+            let bit = puzzle[PuzzleIndex::new(i / 9, i % 9)].bit();
+            *v = bit.trailing_zeros() as u8 + 1;
+        }
+        ret
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn symmetric_removal_does_not_go_below_target_clues() {
+        // 回転対称ではほとんどのヒントが二つずつ消えるので, 目標を下回りやすい
+        for seed in 0..5 {
+            let generated = Generator::new(seed)
+                .symmetry(Symmetry::Rotational)
+                .target_clues(31)
+                .generate();
+            assert!(generated.clue_count() >= 31);
+            assert!(generated.puzzle().is_unique());
+        }
+    }
+
+    #[test]
+    fn unconstrained_removal_is_minimal() {
+        let generated = Generator::new(1).generate();
+        assert!(generated.puzzle().is_unique());
+        assert!(generated.is_minimal());
+    }
+
+    #[test]
+    fn same_seed_gives_same_puzzle() {
+        let a = Generator::new(7).symmetry(Symmetry::Mirror).generate();
+        let b = Generator::new(7).symmetry(Symmetry::Mirror).generate();
+        assert_eq!(a.clues(), b.clues());
+        assert_eq!(a.solution(), b.solution());
+    }
+
+    #[test]
+    fn generate_requiring_returns_puzzle_whose_hardest_technique_matches() {
+        let generated = Generator::new(0)
+            .generate_requiring(TechniqueKind::Pointing, 20)
+            .unwrap();
+        let rating = rate(&generated.puzzle()).unwrap();
+        assert_eq!(rating.hardest(), Some(TechniqueKind::Pointing));
+        assert_eq!(rating.tier(), Tier::Medium);
+    }
+
+    #[test]
+    fn generate_in_band_returns_puzzle_within_band() {
+        let generated = Generator::new(0).generate_in_band(500..800, 20).unwrap();
+        let rating = rate(&generated.puzzle()).unwrap();
+        assert!((500..800).contains(&rating.score()));
+        assert_ne!(rating.tier(), Tier::BeyondSolver);
+    }
+}
@@ -1,3 +1,5 @@
 pub mod cell;
+pub mod generator;
 pub mod puzzle;
+pub mod rating;
 pub use puzzle::Puzzle;
\ No newline at end of file
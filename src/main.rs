@@ -1,4 +1,7 @@
-use std::{fs::{read, File}, io::{stdin, Write}};
+use std::{
+    fs::{read, File},
+    io::{stdin, Write},
+};
 
 use newsudoku::{puzzle::PuzzleIndex, Puzzle};
 
@@ -8,25 +11,28 @@ fn main() {
     let puzzle_seed = puzzle_seed.trim();
     if puzzle_seed.len() != 81 {
         println!("{}", puzzle_seed);
-        eprintln!("puzzle seed length must be 81 given is {}", puzzle_seed.len());
+        eprintln!(
+            "puzzle seed length must be 81 given is {}",
+            puzzle_seed.len()
+        );
         return;
     }
     let mut puzzle = Puzzle::new();
-    puzzle_seed
+    for (i, c) in puzzle_seed
         .chars()
         .enumerate()
         .filter(|(_, c)| c.is_numeric())
-        .for_each(|(i, c)| {
-            let col = i / 9;
-            let row = i % 9;
-            puzzle.fill(PuzzleIndex::new(col, row), c.to_digit(10).unwrap() as u8);
-        });
-    while {
-        let hash = puzzle.hash();
-        puzzle.solve();
-        hash != puzzle.hash()
-    } {}
-    puzzle.validate();
+    {
+        let col = i / 9;
+        let row = i % 9;
+        if let Err(e) = puzzle.fill(PuzzleIndex::new(col, row), c.to_digit(10).unwrap() as u8) {
+            eprintln!("{}", e);
+            return;
+        }
+    }
+    if let Err(e) = puzzle.solve_to_fixpoint() {
+        eprintln!("{}", e);
+    }
     let mut string = read(".html").unwrap();
     let mut file = File::create("index.html").unwrap();
     for i in 0..9 {
@@ -40,24 +46,35 @@ fn main() {
         }
     }
     string.pop();
-    string.append(b"`</script><script src=\".js\"></script></body></html>".to_vec().as_mut());
+    string.append(
+        b"`</script><script src=\".js\"></script></body></html>"
+            .to_vec()
+            .as_mut(),
+    );
     file.write_all(&string[..]).unwrap();
     // open ./index.html in browser
+    // ブラウザの終了は待たない. 子プロセスはここで手放す
     #[cfg(target_os = "windows")]
-    std::process::Command::new("cmd")
-        .arg("/C")
-        .arg("start")
-        .arg("index.html")
-        .spawn()
-        .unwrap();
+    drop(
+        std::process::Command::new("cmd")
+            .arg("/C")
+            .arg("start")
+            .arg("index.html")
+            .spawn()
+            .unwrap(),
+    );
     #[cfg(target_os = "linux")]
-    std::process::Command::new("xdg-open")
-        .arg("index.html")
-        .spawn()
-        .unwrap();
+    drop(
+        std::process::Command::new("xdg-open")
+            .arg("index.html")
+            .spawn()
+            .unwrap(),
+    );
     #[cfg(target_os = "macos")]
-    std::process::Command::new("open")
-        .arg("index.html")
-        .spawn()
-        .unwrap();
+    drop(
+        std::process::Command::new("open")
+            .arg("index.html")
+            .spawn()
+            .unwrap(),
+    );
 }
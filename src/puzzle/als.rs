@@ -0,0 +1,310 @@
+use std::collections::HashSet;
+
+use super::{
+    puzzle::{bits, index, peer_masks},
+    step::TechniqueKind,
+    Puzzle, SliceType, Step,
+};
+
+// Almost Locked Set. マスは (col * 9 + row) 番目のビットで持つ
+#[derive(Debug, Clone)]
+pub(super) struct Als {
+    cells: u128,
+    digits: u16,
+    // 数字ごとの, その数字を候補に持つマス
+    by_digit: [u128; 9],
+    // 数字ごとの, その数字を候補に持つすべてのマスが見えるマス
+    seen: [u128; 9],
+}
+
+impl Als {
+    fn has(&self, k: usize) -> bool {
+        self.digits & (1 << k) != 0
+    }
+
+    // kがselfとotherの両方に入ることはない (Restricted Common Candidate) か
+    fn is_restricted(&self, other: &Als, k: usize) -> bool {
+        self.cells & other.cells == 0
+            && self.has(k)
+            && other.has(k)
+            && self.by_digit[k] & !other.seen[k] == 0
+    }
+
+    // RCCとなる数字のビット列
+    fn restricted(&self, other: &Als) -> u16 {
+        if self.cells & other.cells != 0 {
+            return 0;
+        }
+        (0..9)
+            .filter(|&k| self.is_restricted(other, k))
+            .fold(0, |acc, k| acc | 1 << k)
+    }
+}
+
+impl Puzzle {
+    // すべての行/列/ブロックのAlmost Locked Set. 複数のスライスに含まれるものは一つにまとめる
+    pub(super) fn almost_locked_sets(&self) -> Vec<Als> {
+        let peers = peer_masks();
+        let mut found = HashSet::new();
+        let mut ret = vec![];
+        for i in 0..9 {
+            for type_ in [SliceType::Row(i), SliceType::Col(i), SliceType::Block(i)] {
+                for (positions, digits) in self.slice(type_).almost_locked_sets() {
+                    let cells = positions
+                        .iter()
+                        .fold(0u128, |acc, p| acc | 1 << (p.col() * 9 + p.row()));
+                    if !found.insert(cells) {
+                        continue;
+                    }
+                    let by_digit = [0, 1, 2, 3, 4, 5, 6, 7, 8].map(|k| {
+                        positions
+                            .iter()
+                            .filter(|p| self[**p].bit() & (1 << k) != 0)
+                            .fold(0u128, |acc, p| acc | 1 << (p.col() * 9 + p.row()))
+                    });
+                    let seen = by_digit.map(|m| bits(m).fold(u128::MAX, |acc, i| acc & peers[i]));
+                    ret.push(Als {
+                        cells,
+                        digits,
+                        by_digit,
+                        seen,
+                    });
+                }
+            }
+        }
+        ret
+    }
+
+    // RCCでつながる二つのALSは, RCC以外の共通の数字zをどちらかに持つので, 両方のzのマスが見えるマスからzを取り除く.
+    // RCCが二つあれば (Doubly Linked) 二つのALSはともにLocked Setとなり, それぞれの数字を見えるマスから取り除ける.
+    pub(super) fn als_xz(&self) -> Vec<Step> {
+        let sets = self.almost_locked_sets();
+        let candidates = self.candidate_masks();
+        let mut ret = vec![];
+        for (i, a) in sets.iter().enumerate() {
+            for b in &sets[i + 1..] {
+                let rcc = a.restricted(b);
+                if rcc == 0 || rcc.count_ones() > 2 {
+                    continue;
+                }
+                let mut eliminations = [0u128; 9];
+                for z in (0..9).filter(|&z| a.has(z) && b.has(z)) {
+                    if rcc.count_ones() == 1 && rcc & (1 << z) != 0 {
+                        continue;
+                    }
+                    eliminations[z] |= a.seen[z] & b.seen[z] & candidates[z];
+                }
+                if rcc.count_ones() == 2 {
+                    for als in [a, b] {
+                        for d in (0..9).filter(|&d| als.has(d) && rcc & (1 << d) == 0) {
+                            eliminations[d] |= als.seen[d] & candidates[d];
+                        }
+                    }
+                }
+                let (cells, digits) = union(&[a, b]);
+                ret.extend(self.als_step(TechniqueKind::AlsXz, cells, digits, eliminations));
+            }
+        }
+        ret
+    }
+
+    // ALSのAとBがそれぞれ別の数字x, yのRCCでCとつながるとき, AかBのどちらかはCにつながらない数字でLocked Setとなるので,
+    // AとBに共通する数字z (x, y以外) を, 両方のzのマスが見えるマスから取り除く.
+    pub(super) fn als_xy_wing(&self) -> Vec<Step> {
+        let sets = self.almost_locked_sets();
+        let candidates = self.candidate_masks();
+        let mut ret = vec![];
+        // ALSごとの, RCCでつながるALSとその数字
+        let mut links = vec![vec![]; sets.len()];
+        for i in 0..sets.len() {
+            for j in i + 1..sets.len() {
+                let rcc = sets[i].restricted(&sets[j]);
+                for x in (0..9).filter(|x| rcc & (1 << x) != 0) {
+                    links[i].push((&sets[j], x));
+                    links[j].push((&sets[i], x));
+                }
+            }
+        }
+        for (c, links) in sets.iter().zip(&links) {
+            for (i, &(a, x)) in links.iter().enumerate() {
+                for &(b, y) in &links[i + 1..] {
+                    if x == y || a.cells & b.cells != 0 {
+                        continue;
+                    }
+                    let mut eliminations = [0u128; 9];
+                    for z in (0..9).filter(|&z| z != x && z != y && a.has(z) && b.has(z)) {
+                        eliminations[z] = a.seen[z] & b.seen[z] & candidates[z];
+                    }
+                    let (cells, digits) = union(&[a, b, c]);
+                    ret.extend(self.als_step(
+                        TechniqueKind::AlsXyWing,
+                        cells,
+                        digits,
+                        eliminations,
+                    ));
+                }
+            }
+        }
+        ret
+    }
+
+    // 茎となるマスの各候補dについて, dを持つマスがすべて茎から見えるALS (花びら) があれば, 茎に入る数字の花びらはLocked Setとなる.
+    // すべての花びらに共通する数字zを, すべての花びらのzのマスが見えるマスから取り除く.
+    pub(super) fn death_blossom(&self) -> Vec<Step> {
+        let sets = self.almost_locked_sets();
+        let candidates = self.candidate_masks();
+        let peers = peer_masks();
+        let mut ret = vec![];
+        for stem in 0..81 {
+            let cell = &self[index(stem)];
+            if cell.is_filled() || cell.bit().count_ones() > 4 {
+                continue;
+            }
+            let digits = (0..9)
+                .filter(|k| cell.bit() & (1 << k) != 0)
+                .collect::<Vec<_>>();
+            for z in (0..9).filter(|k| cell.bit() & (1 << k) == 0) {
+                // 茎の候補ごとの花びらの候補
+                let petals = digits
+                    .iter()
+                    .map(|&d| {
+                        sets.iter()
+                            .filter(|s| {
+                                s.cells & (1 << stem) == 0
+                                    && s.has(d)
+                                    && s.has(z)
+                                    && s.by_digit[d] & !peers[stem] == 0
+                            })
+                            .collect::<Vec<_>>()
+                    })
+                    .collect::<Vec<_>>();
+                let mut chosen = vec![];
+                if let Some(mask) = blossom(&petals, &mut chosen, candidates[z], z) {
+                    let (cells, digits) = union(&chosen);
+                    let mut eliminations = [0u128; 9];
+                    eliminations[z] = mask;
+                    ret.extend(self.als_step(
+                        TechniqueKind::DeathBlossom,
+                        cells | 1 << stem,
+                        digits | cell.bit(),
+                        eliminations,
+                    ));
+                }
+            }
+        }
+        ret
+    }
+
+    // 取り除くものがあるときだけ手順を返す
+    fn als_step(
+        &self,
+        technique: TechniqueKind,
+        cells: u128,
+        digits: u16,
+        eliminations: [u128; 9],
+    ) -> Option<Step> {
+        if eliminations.iter().all(|&m| m == 0) {
+            return None;
+        }
+        let mut step = Step::new(technique, bits(cells).map(index).collect(), digits);
+        for (k, &mask) in eliminations.iter().enumerate() {
+            for i in bits(mask) {
+                step.eliminate(&self[index(i)], 1 << k);
+            }
+        }
+        Some(step)
+    }
+}
+
+// ALSのマスと数字をすべて合わせたもの
+fn union(sets: &[&Als]) -> (u128, u16) {
+    sets.iter()
+        .fold((0, 0), |(c, d), s| (c | s.cells, d | s.digits))
+}
+
+// 茎の候補ごとに互いに重ならない花びらを一つずつ選び, 取り除ける数字zのマスがあればそのマスを返す.
+// maskはここまでに選んだ花びらのzのマスがすべて見えるマス
+fn blossom<'a>(
+    petals: &[Vec<&'a Als>],
+    chosen: &mut Vec<&'a Als>,
+    mask: u128,
+    z: usize,
+) -> Option<u128> {
+    if mask == 0 {
+        return None;
+    }
+    let Some((first, rest)) = petals.split_first() else {
+        return Some(mask);
+    };
+    for &petal in first {
+        if chosen.iter().any(|c| c.cells & petal.cells != 0) {
+            continue;
+        }
+        chosen.push(petal);
+        if let Some(mask) = blossom(rest, chosen, mask & petal.seen[z], z) {
+            return Some(mask);
+        }
+        chosen.pop();
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::puzzle::{assert_finds, parse_candidates};
+    use super::*;
+
+    #[test]
+    fn als_xz() {
+        let puzzle = parse_candidates(
+            "
+             57  4    29 257 8    1  3   6     279
+             3   127  6  4   2579 27 8   12579 279
+             157 1279 8  3   2579 6  159 12579 4
+             17  78   4  257 157  3  279 289   6
+             9   1278 5  6   127  27 4   28    3
+             6   237  23 9   4    8  57  257   1
+             4   39   39 18  27   5  6   127   278
+             2   5    7  18  6    4  19  3     89
+             8   6    1  27  3    9  27  4     5
+            ",
+        );
+        assert_finds(&puzzle, TechniqueKind::AlsXz, "r2c8<>1");
+    }
+
+    #[test]
+    fn als_xy_wing() {
+        let puzzle = parse_candidates(
+            "
+             6   234  234   34589 2589 7   1    4589 358
+             8   347  1     3459  59   6   4579 2    35
+             9   2347 5     1     28   34  467  3478 368
+             157 3456 34679 35679 69   2   8    1345 135
+             15  68   689   689   4    35  2    135  7
+             2   3458 3478  3578  158  135 45   6    9
+             57  1    67    2     3    8   5679 579  4
+             4   2568 268   56    7    9   3    158  1568
+             3   9    678   456   156  145 567  578  2
+            ",
+        );
+        assert_finds(&puzzle, TechniqueKind::AlsXyWing, "r1c4<>9");
+    }
+
+    #[test]
+    fn death_blossom() {
+        let puzzle = parse_candidates(
+            "
+             4   1   256 25   9    26    8   7  3
+             68  9   7   138  3468 13468 14  5  2
+             3   58  25  1258 7    1248  9   46 16
+             168 468 469 139  1356 1346  145 2  7
+             5   7   49  1289 148  1248  3   46 16
+             16  2   3   7    456  146   45  8  9
+             2   46  146 138  138  5     7   9  48
+             9   3   8   4    2    7     6   1  5
+             7   45  145 6    18   9     2   3  48
+            ",
+        );
+        assert_finds(&puzzle, TechniqueKind::DeathBlossom, "r2c5<>8");
+    }
+}
@@ -0,0 +1,205 @@
+use super::{Puzzle, PuzzleIndex};
+
+#[derive(Clone)]
+pub enum Uniqueness {
+    NoSolution,
+    Unique(Puzzle),
+    // 最初に見つかった二つの解
+    Multiple(Puzzle, Puzzle),
+}
+
+// 盤面をビット列で持ち, 候補が最も少ないマスから順に試していくバックトラック探索.
+#[derive(Clone)]
+struct Backtracker {
+    // マス (col * 9 + row) ごとの候補
+    candidates: [u16; 81],
+    // マスごとの確定した数字. 0は未確定
+    values: [u8; 81],
+    cols: [u16; 9],
+    rows: [u16; 9],
+    blocks: [u16; 9],
+}
+
+impl Backtracker {
+    fn new(puzzle: &Puzzle) -> Option<Self> {
+        let mut ret = Self {
+            candidates: [0; 81],
+            values: [0; 81],
+            cols: [0; 9],
+            rows: [0; 9],
+            blocks: [0; 9],
+        };
+        for i in 0..81 {
+            let cell = &puzzle[PuzzleIndex::new(i / 9, i % 9)];
+            ret.candidates[i] = cell.bit();
+        }
+        for i in 0..81 {
+            let pos = PuzzleIndex::new(i / 9, i % 9);
+            if puzzle[pos].is_filled() && !ret.place(i, puzzle[pos].bit()) {
+                // 同じ行/列/ブロックにfilledが同じ値を持っている
+                return None;
+            }
+        }
+        Some(ret)
+    }
+
+    fn place(&mut self, i: usize, bit: u16) -> bool {
+        let (col, row) = (i / 9, i % 9);
+        let block = col / 3 * 3 + row / 3;
+        if (self.cols[col] | self.rows[row] | self.blocks[block]) & bit != 0 {
+            return false;
+        }
+        self.cols[col] |= bit;
+        self.rows[row] |= bit;
+        self.blocks[block] |= bit;
+        self.values[i] = bit.trailing_zeros() as u8 + 1;
+        true
+    }
+
+    fn remove(&mut self, i: usize) {
+        let (col, row) = (i / 9, i % 9);
+        let block = col / 3 * 3 + row / 3;
+        let bit = !(1u16 << (self.values[i] - 1));
+        self.cols[col] &= bit;
+        self.rows[row] &= bit;
+        self.blocks[block] &= bit;
+        self.values[i] = 0;
+    }
+
+    fn available(&self, i: usize) -> u16 {
+        let (col, row) = (i / 9, i % 9);
+        let block = col / 3 * 3 + row / 3;
+        self.candidates[i] & !(self.cols[col] | self.rows[row] | self.blocks[block])
+    }
+
+    // 未確定のマスのうち候補が最も少ないものを返す. すべて確定していればNone
+    fn most_constrained(&self) -> Option<(usize, u16)> {
+        let mut ret: Option<(usize, u16)> = None;
+        for i in 0..81 {
+            if self.values[i] != 0 {
+                continue;
+            }
+            let bit = self.available(i);
+            if ret.is_none_or(|(_, b)| bit.count_ones() < b.count_ones()) {
+                ret = Some((i, bit));
+                if bit.count_ones() <= 1 {
+                    break;
+                }
+            }
+        }
+        ret
+    }
+
+    // 解を見つけるたびにfoundを呼び, falseが返ってきたら探索を打ち切る.
+    fn search(&mut self, found: &mut impl FnMut(&[u8; 81]) -> bool) -> bool {
+        let Some((i, mut bit)) = self.most_constrained() else {
+            return found(&self.values);
+        };
+        while bit != 0 {
+            let b = bit & bit.wrapping_neg();
+            bit &= !b;
+            self.place(i, b);
+            let keep_going = self.search(found);
+            self.remove(i);
+            if !keep_going {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+impl Puzzle {
+    pub fn solve_by_backtracking(&mut self) -> bool {
+        // 現在の候補の状態から探索するので, 論理的な解法の途中からでも使える.
+        let Some(solution) = self.solutions(1).pop() else {
+            return false;
+        };
+        self.fill_solution(solution);
+        true
+    }
+
+    pub fn count_solutions(&self, limit: usize) -> usize {
+        self.solutions(limit).len()
+    }
+
+    pub fn is_unique(&self) -> bool {
+        matches!(self.uniqueness(), Uniqueness::Unique(..))
+    }
+
+    pub fn uniqueness(&self) -> Uniqueness {
+        let mut solutions = self.solutions(2).into_iter().map(|values| {
+            let mut puzzle = self.clone();
+            puzzle.fill_solution(values);
+            puzzle
+        });
+        match (solutions.next(), solutions.next()) {
+            (Some(first), Some(second)) => Uniqueness::Multiple(first, second),
+            (Some(first), None) => Uniqueness::Unique(first),
+            _ => Uniqueness::NoSolution,
+        }
+    }
+
+    // limit個の解が見つかった時点で探索を打ち切る.
+    fn solutions(&self, limit: usize) -> Vec<[u8; 81]> {
+        let mut ret = vec![];
+        if limit == 0 {
+            return ret;
+        }
+        let Some(mut backtracker) = Backtracker::new(self) else {
+            return ret;
+        };
+        backtracker.search(&mut |values| {
+            ret.push(*values);
+            ret.len() < limit
+        });
+        ret
+    }
+
+    fn fill_solution(&mut self, values: [u8; 81]) {
+        // 解なので矛盾しない
+        for (i, v) in values.into_iter().enumerate() {
+            let _ = self.fill(PuzzleIndex::new(i / 9, i % 9), v);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::puzzle::{index, parse};
+    use super::*;
+
+    const PUZZLE: &str =
+        "800000000003600000070090200050007000000045700000100030001000068008500010090000400";
+    const SOLUTION: &str =
+        "812753649943682175675491283154237896369845721287169534521974368438526917796318452";
+
+    #[test]
+    fn solves_unique_puzzle() {
+        // 候補を消していくだけでは解けない
+        let mut puzzle = parse(PUZZLE);
+        assert!((0..81).any(|i| !puzzle[index(i)].is_filled()));
+        assert!(puzzle.is_unique());
+        assert!(puzzle.solve_by_backtracking());
+        for (i, c) in SOLUTION.chars().enumerate() {
+            let cell = &puzzle[index(i)];
+            assert!(cell.is_filled());
+            assert_eq!(cell.bit(), 1 << (c.to_digit(10).unwrap() - 1));
+        }
+    }
+
+    #[test]
+    fn under_clued_grid_has_multiple_solutions() {
+        // 解の上2行だけを残す
+        let puzzle = parse(&SOLUTION[..18]);
+        assert_eq!(puzzle.count_solutions(2), 2);
+        let Uniqueness::Multiple(first, second) = puzzle.uniqueness() else {
+            panic!("expected multiple solutions");
+        };
+        for solution in [&first, &second] {
+            assert!((0..81).all(|i| solution[index(i)].is_filled()));
+            assert!(solution.validate().is_ok());
+        }
+        assert_ne!(first.fingerprint(), second.fingerprint());
+    }
+}
@@ -0,0 +1,467 @@
+use std::collections::{HashMap, HashSet};
+
+use super::{
+    puzzle::{bits, index, peer_masks},
+    step::TechniqueKind,
+    Puzzle, PuzzleIndex, SliceType, Step,
+};
+
+// 鎖の節点. 数字digitが一つのマスか, ブロックと行/列の交差部分にある2,3マスのどれかに入ることを表す.
+// マスは (col * 9 + row) 番目のビットで持つ
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct Node {
+    cells: u128,
+    digit: usize,
+}
+
+impl Node {
+    fn is_group(&self) -> bool {
+        self.cells.count_ones() > 1
+    }
+
+    fn positions(&self) -> Vec<PuzzleIndex> {
+        bits(self.cells).map(index).collect()
+    }
+}
+
+// 使ってよい節点とリンクの種類
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum ChainKind {
+    // 一つの数字の行/列/ブロック内のリンクのみ
+    X,
+    // 強リンクは二択のマスの中だけ
+    XY,
+    Aic,
+    // グループ化した節点も使う
+    Grouped,
+}
+
+// 節点の間の強リンク (一方が偽なら他方が真) と弱リンク (一方が真なら他方が偽)
+struct ChainGraph {
+    nodes: Vec<Node>,
+    strong: Vec<Vec<usize>>,
+    weak: Vec<Vec<usize>>,
+    // 数字ごとの未確定の候補のあるマス
+    candidates: [u128; 9],
+    // 節点のすべてのマスが見えるマス
+    seen: Vec<u128>,
+}
+
+impl ChainGraph {
+    fn new(puzzle: &Puzzle, kind: ChainKind) -> Self {
+        let candidates = puzzle.candidate_masks();
+        let peers = peer_masks();
+        let unit = |type_: SliceType| {
+            puzzle.slice(type_).iter().fold(0u128, |acc, c| {
+                acc | 1 << (c.pos().col() * 9 + c.pos().row())
+            })
+        };
+        let units = (0..9)
+            .flat_map(|i| [SliceType::Row(i), SliceType::Col(i), SliceType::Block(i)])
+            .map(unit)
+            .collect::<Vec<_>>();
+
+        let mut nodes = vec![];
+        for (k, &mask) in candidates.iter().enumerate() {
+            nodes.extend(bits(mask).map(|i| Node {
+                cells: 1 << i,
+                digit: k,
+            }));
+        }
+        if kind == ChainKind::Grouped {
+            for b in 0..9 {
+                let block = unit(SliceType::Block(b));
+                let lines = (0..3)
+                    .map(|t| unit(SliceType::Row(b % 3 * 3 + t)))
+                    .chain((0..3).map(|t| unit(SliceType::Col(b / 3 * 3 + t))));
+                for line in lines {
+                    for (k, &mask) in candidates.iter().enumerate() {
+                        let cells = bits(block & line & mask).collect::<Vec<_>>();
+                        // 交差部分の候補のうち2マス以上の組はすべて節点になる
+                        for sub in 3..(1 << cells.len()) {
+                            let group = (0..cells.len())
+                                .filter(|t| sub & (1 << t) != 0)
+                                .fold(0u128, |acc, t| acc | 1 << cells[t]);
+                            if group.count_ones() > 1 {
+                                nodes.push(Node {
+                                    cells: group,
+                                    digit: k,
+                                });
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        let lookup = nodes
+            .iter()
+            .enumerate()
+            .map(|(i, n)| (*n, i))
+            .collect::<HashMap<_, _>>();
+        let seen = nodes
+            .iter()
+            .map(|n| bits(n.cells).fold(u128::MAX, |acc, i| acc & peers[i]))
+            .collect::<Vec<_>>();
+
+        let mut strong = vec![vec![]; nodes.len()];
+        if kind != ChainKind::XY {
+            // 行/列/ブロックの中の数字の候補が二つの節点に分かれる
+            for &u in &units {
+                for (a, node) in nodes.iter().enumerate() {
+                    let all = candidates[node.digit] & u;
+                    if node.cells & !all != 0 || node.cells == all {
+                        continue;
+                    }
+                    let other = Node {
+                        cells: all & !node.cells,
+                        digit: node.digit,
+                    };
+                    if let Some(&b) = lookup.get(&other) {
+                        if !strong[a].contains(&b) {
+                            strong[a].push(b);
+                        }
+                    }
+                }
+            }
+        }
+        if kind != ChainKind::X {
+            // 候補が二つしかないマス
+            for i in 0..81 {
+                let digits = (0..9)
+                    .filter(|&k| candidates[k] & (1 << i) != 0)
+                    .collect::<Vec<_>>();
+                if digits.len() != 2 {
+                    continue;
+                }
+                let [a, b] = [0, 1].map(|t| {
+                    lookup[&Node {
+                        cells: 1 << i,
+                        digit: digits[t],
+                    }]
+                });
+                strong[a].push(b);
+                strong[b].push(a);
+            }
+        }
+
+        let in_cell = matches!(kind, ChainKind::Aic | ChainKind::Grouped);
+        let mut weak = vec![vec![]; nodes.len()];
+        for (a, x) in nodes.iter().enumerate() {
+            for (b, y) in nodes.iter().enumerate() {
+                let linked = if x.digit == y.digit {
+                    x.cells & y.cells == 0 && seen[a] & y.cells == y.cells
+                } else {
+                    in_cell && !x.is_group() && x.cells == y.cells
+                };
+                if linked {
+                    weak[a].push(b);
+                }
+            }
+        }
+        Self {
+            nodes,
+            strong,
+            weak,
+            candidates,
+            seen,
+        }
+    }
+
+    // 節点aが真なら偽になる, 数字kの候補のあるマス
+    fn weak_mask(&self, a: usize, k: usize) -> u128 {
+        let node = self.nodes[a];
+        let mask = if node.digit == k {
+            self.seen[a]
+        } else if node.is_group() {
+            0
+        } else {
+            node.cells
+        };
+        mask & self.candidates[k]
+    }
+
+    fn is_weak(&self, a: usize, b: usize) -> bool {
+        self.weak[a].contains(&b)
+    }
+
+    // startを偽とした強リンクから始めて, 強弱を交互にたどる幅優先探索.
+    // 状態 (節点 * 2 + 真なら1) ごとの一つ前の状態と, 始点からの節点の数を返す
+    fn search(&self, start: usize, max_length: usize) -> (Vec<usize>, Vec<usize>) {
+        let mut parent = vec![usize::MAX; self.nodes.len() * 2];
+        let mut depth = vec![0; self.nodes.len() * 2];
+        parent[start * 2] = start * 2;
+        depth[start * 2] = 1;
+        let mut queue = std::collections::VecDeque::from([start * 2]);
+        while let Some(state) = queue.pop_front() {
+            if depth[state] >= max_length {
+                continue;
+            }
+            let (v, on) = (state / 2, state % 2 == 1);
+            let next = if on { &self.weak[v] } else { &self.strong[v] };
+            for &w in next {
+                let s = w * 2 + if on { 0 } else { 1 };
+                if parent[s] != usize::MAX {
+                    continue;
+                }
+                parent[s] = state;
+                depth[s] = depth[state] + 1;
+                queue.push_back(s);
+            }
+        }
+        (parent, depth)
+    }
+
+    fn path(&self, parent: &[usize], end: usize) -> Vec<usize> {
+        let mut path = vec![end / 2];
+        let mut state = end;
+        while parent[state] != state {
+            state = parent[state];
+            path.push(state / 2);
+        }
+        path.reverse();
+        path
+    }
+
+    // Eureka記法. 同じマスで続く節点は (1=2)r1c1 のようにまとめる
+    fn eureka(&self, path: &[usize], is_loop: bool) -> String {
+        let link = |i: usize| if i.is_multiple_of(2) { "=" } else { "-" };
+        let mut ret = String::new();
+        let mut i = 0;
+        while i < path.len() {
+            let node = self.nodes[path[i]];
+            ret.push('(');
+            ret.push(char::from(b'1' + node.digit as u8));
+            while i + 1 < path.len() && self.nodes[path[i + 1]].cells == node.cells {
+                ret.push_str(link(i));
+                ret.push(char::from(b'1' + self.nodes[path[i + 1]].digit as u8));
+                i += 1;
+            }
+            ret.push(')');
+            ret.push_str(&cells_to_string(&node.positions()));
+            if i + 1 < path.len() {
+                ret.push_str(link(i));
+            }
+            i += 1;
+        }
+        if is_loop {
+            let first = self.nodes[path[0]];
+            ret.push_str(&format!(
+                "-({}){}",
+                first.digit + 1,
+                cells_to_string(&first.positions())
+            ));
+        }
+        ret
+    }
+}
+
+// r1c23 や r12c3 のようにまとめて書く. 画面の行はcol, 列はrow
+fn cells_to_string(cells: &[PuzzleIndex]) -> String {
+    let join = |v: Vec<usize>| v.iter().map(|x| (x + 1).to_string()).collect::<String>();
+    if cells.iter().all(|p| p.col() == cells[0].col()) {
+        format!(
+            "r{}c{}",
+            cells[0].col() + 1,
+            join(cells.iter().map(|p| p.row()).collect())
+        )
+    } else {
+        format!(
+            "r{}c{}",
+            join(cells.iter().map(|p| p.col()).collect()),
+            cells[0].row() + 1
+        )
+    }
+}
+
+// 鎖を探すときの節点の数の上限の既定値
+pub(super) const MAX_CHAIN_LENGTH: usize = 12;
+
+impl Puzzle {
+    // 両端の節点の少なくとも一方が真となる交互推論の鎖 (AIC) と, 閉じた鎖 (Nice Loop) を探す.
+    // 鎖の両端のどちらにも弱リンクでつながる候補は偽. 閉じた鎖では弱リンクがすべて強リンクにもなるので,
+    // 各弱リンクの両端につながる候補が偽となる. 鎖の始点と終点が同じならその節点は真となる.
+    // 節点の数がmax_length以下の鎖だけを探す.
+    pub(super) fn chains(&self, technique: TechniqueKind, max_length: usize) -> Vec<Step> {
+        let kind = match technique {
+            TechniqueKind::XChain | TechniqueKind::XCycle => ChainKind::X,
+            TechniqueKind::XYChain => ChainKind::XY,
+            TechniqueKind::GroupedAIC => ChainKind::Grouped,
+            _ => ChainKind::Aic,
+        };
+        let graph = ChainGraph::new(self, kind);
+        let mut found = HashSet::new();
+        let mut ret = vec![];
+        for start in 0..graph.nodes.len() {
+            let (parent, depth) = graph.search(start, max_length);
+            for end in 0..graph.nodes.len() {
+                let state = end * 2 + 1;
+                if parent[state] == usize::MAX || depth[state] < 4 {
+                    continue;
+                }
+                let is_loop = end != start && graph.is_weak(end, start);
+                match technique {
+                    TechniqueKind::XChain if is_loop || end == start => continue,
+                    TechniqueKind::XCycle if !is_loop && end != start => continue,
+                    _ => {}
+                }
+                // 閉じていない鎖は両端だけで消去が決まるので, 先に確かめる
+                if !is_loop
+                    && end != start
+                    && (0..9).all(|k| graph.weak_mask(start, k) & graph.weak_mask(end, k) == 0)
+                {
+                    continue;
+                }
+                let path = graph.path(&parent, state);
+                let inner = &path[..path.len() - 1];
+                if inner
+                    .iter()
+                    .enumerate()
+                    .any(|(i, v)| inner[..i].contains(v))
+                    || (end != start && inner.contains(&end))
+                    || (technique == TechniqueKind::GroupedAIC
+                        && !path.iter().any(|&v| graph.nodes[v].is_group()))
+                {
+                    continue;
+                }
+                let mut key = if is_loop {
+                    path.clone()
+                } else {
+                    vec![start, end]
+                };
+                key.sort();
+                if !found.insert((is_loop, key)) {
+                    continue;
+                }
+                ret.push(self.chain_step(&graph, technique, &path, is_loop));
+            }
+        }
+        ret
+    }
+
+    fn chain_step(
+        &self,
+        graph: &ChainGraph,
+        technique: TechniqueKind,
+        path: &[usize],
+        is_loop: bool,
+    ) -> Step {
+        let mut cells = vec![];
+        let mut digits = 0;
+        for &v in path {
+            digits |= 1 << graph.nodes[v].digit;
+            for p in graph.nodes[v].positions() {
+                if !cells.contains(&p) {
+                    cells.push(p);
+                }
+            }
+        }
+        let mut step = Step::new(technique, cells, digits);
+        let (first, last) = (path[0], path[path.len() - 1]);
+        // 偽となる候補の対. 閉じた鎖ならすべての弱リンク
+        let pairs = if is_loop {
+            (1..path.len())
+                .step_by(2)
+                .map(|i| (path[i], path[(i + 1) % path.len()]))
+                .collect()
+        } else {
+            vec![(first, last)]
+        };
+        for (a, b) in pairs {
+            for k in 0..9 {
+                for i in bits(graph.weak_mask(a, k) & graph.weak_mask(b, k)) {
+                    step.eliminate(&self[index(i)], 1 << k);
+                }
+            }
+        }
+        let node = graph.nodes[first];
+        if first == last && !node.is_group() {
+            step.place(&self[node.positions()[0]], node.digit as u8 + 1);
+        }
+        step.with_chain(graph.eureka(path, is_loop))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::puzzle::{assert_finds, parse_candidates};
+    use super::*;
+
+    #[test]
+    fn xchain() {
+        let puzzle = parse_candidates(
+            "
+             46     3     5     27    46   1    2789  789   2789
+             1267   1268  9     237   5    2678 13    4     27
+             1247   1248  1278  2379  2348 2789 13    6     5
+             8      1259  123   12379 1236 4    2579  579   2679
+             2349   249   23    5     236  2679 24789 1     26789
+             12459  7     6     8     12   29   2459  59    3
+             59     589   4     6     7    3    589   2     1
+             123567 12568 12378 4     9    258  578   3578  78
+             23579  259   2378  12    128  258  6     35789 4
+            ",
+        );
+        assert_finds(&puzzle, TechniqueKind::XChain, "r9c8<>8");
+        // 見つかる鎖は6個の節点からなる
+        assert!(puzzle.chains(TechniqueKind::XChain, 5).is_empty());
+        assert!(!puzzle.chains(TechniqueKind::XChain, 6).is_empty());
+    }
+
+    #[test]
+    fn xychain() {
+        let puzzle = parse_candidates(
+            "
+             1678  2    678 5    3   17  4   89 679
+             5     147  467 1278 278 9   3   28 67
+             78    3    9   28   4   6   5   1  27
+             9     6    3   18   58  15  2   7  4
+             47    5    2   47   9   3   8   6  1
+             1478  1478 478 247  6   27  9   3  5
+             23467 47   467 9    127 8   167 5  23
+             23678 789  1   36   257 257 67  4  239
+             2367  79   5   36   127 4   167 29 8
+            ",
+        );
+        assert_finds(&puzzle, TechniqueKind::XYChain, "r6c4<>2");
+    }
+
+    #[test]
+    fn aic() {
+        let puzzle = parse_candidates(
+            "
+             1   5  8 3   6   4   7   2   9
+             7   3  4 29  29  1   68  68  5
+             2   6  9 57  578 58  4   3   1
+             3   48 6 1   245 7   9   458 28
+             49  7  2 8   345 59  1   456 36
+             489 1  5 6   234 29  238 48  7
+             458 48 7 245 1   3   256 9   2468
+             6   9  1 245 28  258 235 7   34
+             458 2  3 79  79  6   58  1   48
+            ",
+        );
+        assert_finds(
+            &puzzle,
+            TechniqueKind::AIC,
+            "r4c9=2, r4c5<>2, r6c7<>2, r7c9<>2, r4c9<>8",
+        );
+    }
+
+    #[test]
+    fn grouped_aic() {
+        let puzzle = parse_candidates(
+            "
+             9    36  378  2    157  1567 358 4 135
+             56   46  2    1456 3    8    159 7 19
+             3578 1   3478 457  457  9    358 6 2
+             2368 7   3489 568  89   56   24  1 369
+             36   5   139  167  2    4    39  8 3679
+             268  469 1489 3    1789 167  24  5 679
+             1    8   5    9    6    2    7   3 4
+             4    2   6    78   78   3    15  9 15
+             37   39  379  145  145  15   6   2 8
+            ",
+        );
+        assert_finds(&puzzle, TechniqueKind::GroupedAIC, "r4c9<>9");
+    }
+}
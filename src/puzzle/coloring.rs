@@ -0,0 +1,269 @@
+use super::{puzzle::combinations, step::TechniqueKind, Puzzle, PuzzleIndex, Step};
+
+// 候補 (マス, 数字)
+type Candidate = (PuzzleIndex, usize);
+
+impl Puzzle {
+    // 数字kの強リンクをたどれるマスの集まりごとに, 交互に二色で塗り分けたもの. 同じ色のマスは真偽が一致する
+    pub(super) fn color_components(&self, k: usize) -> Vec<[Vec<PuzzleIndex>; 2]> {
+        paint(&self.strong_links(k))
+    }
+
+    // 同じ色のマスが互いに見えていればその色は偽 (Color Wrap),
+    // 両方の色のマスが見えるマスは偽 (Color Trap).
+    pub(super) fn simple_coloring(&self, technique: TechniqueKind) -> Vec<Step> {
+        let mut ret = vec![];
+        for k in 0..9 {
+            for colors in self.color_components(k) {
+                let cells = colors.concat();
+                let mut step = Step::new(technique, cells.clone(), 1 << k);
+                if technique == TechniqueKind::ColorWrap {
+                    for color in &colors {
+                        if combinations(color, 2).iter().any(|c| c[0].is_peer(&c[1])) {
+                            for p in color {
+                                step.eliminate(&self[*p], 1 << k);
+                            }
+                        }
+                    }
+                } else {
+                    for i in 0..81 {
+                        let p = PuzzleIndex::new(i / 9, i % 9);
+                        if !cells.contains(&p)
+                            && self.sees_any(p, &colors[0])
+                            && self.sees_any(p, &colors[1])
+                        {
+                            step.eliminate(&self[p], 1 << k);
+                        }
+                    }
+                }
+                ret.push(step);
+            }
+        }
+        ret
+    }
+
+    // 二つの塗り分けの色Aと色Cのマスが互いに見えるとき, AとCは同時に真にならないので,
+    // Aの反対色とCの反対色の両方が見えるマスは偽となる. またAがCとその反対色の両方を見ていればAは偽となる.
+    pub(super) fn multi_coloring(&self) -> Vec<Step> {
+        let mut ret = vec![];
+        for k in 0..9 {
+            let components = self.color_components(k);
+            for i in 0..components.len() {
+                for j in 0..components.len() {
+                    if i == j {
+                        continue;
+                    }
+                    let (x, y) = (&components[i], &components[j]);
+                    let cells = x.concat().into_iter().chain(y.concat()).collect::<Vec<_>>();
+                    for a in 0..2 {
+                        for c in 0..2 {
+                            if !x[a].iter().any(|p| self.sees_any(*p, &y[c])) {
+                                continue;
+                            }
+                            let mut step =
+                                Step::new(TechniqueKind::MultiColoring, cells.clone(), 1 << k);
+                            for t in 0..81 {
+                                let p = PuzzleIndex::new(t / 9, t % 9);
+                                if !cells.contains(&p)
+                                    && self.sees_any(p, &x[1 - a])
+                                    && self.sees_any(p, &y[1 - c])
+                                {
+                                    step.eliminate(&self[p], 1 << k);
+                                }
+                            }
+                            if x[a].iter().any(|p| self.sees_any(*p, &y[1 - c])) {
+                                for p in &x[a] {
+                                    step.eliminate(&self[*p], 1 << k);
+                                }
+                            }
+                            ret.push(step);
+                        }
+                    }
+                }
+            }
+        }
+        ret
+    }
+
+    // posからcellsのどれかが見えるか
+    pub(super) fn sees_any(&self, pos: PuzzleIndex, cells: &[PuzzleIndex]) -> bool {
+        cells.iter().any(|c| c.is_peer(&pos))
+    }
+
+    // 数字をまたいで塗り分ける. 強リンクは行/列/ブロックの共役対と, 候補が二つのマスの中の二つの候補.
+    // 同じマスや同じ数字の見えるマスに同じ色が二つあるか, 色のないマスのすべての候補が同じ色から見えていれば, その色は偽.
+    // そうでなければ, 両方の色から見える候補と, 反対の色と同じマスにいてその色の同じ数字から見える候補が偽.
+    pub(super) fn medusa(&self) -> Vec<Step> {
+        let mut links = vec![];
+        for k in 0..9 {
+            links.extend(
+                self.strong_links(k)
+                    .into_iter()
+                    .map(|(a, b)| ((a, k), (b, k))),
+            );
+        }
+        for i in 0..81 {
+            let cell = &self[PuzzleIndex::new(i / 9, i % 9)];
+            if !cell.is_filled() && cell.bit().count_ones() == 2 {
+                let a = cell.bit().trailing_zeros() as usize;
+                let b = 15 - cell.bit().leading_zeros() as usize;
+                links.push(((cell.pos(), a), (cell.pos(), b)));
+            }
+        }
+        let mut ret = vec![];
+        for colors in paint(&links) {
+            let candidates = colors.concat();
+            let mut cells = vec![];
+            let mut digits = 0;
+            for &(p, k) in &candidates {
+                digits |= 1 << k;
+                if !cells.contains(&p) {
+                    cells.push(p);
+                }
+            }
+            let mut step = Step::new(TechniqueKind::Medusa3D, cells.clone(), digits);
+            // posの数字kの候補から見える, colorの候補があるか
+            let sees = |pos: PuzzleIndex, k: usize, color: &[Candidate]| {
+                color.iter().any(|&(p, d)| d == k && p.is_peer(&pos))
+            };
+            let unfilled = (0..81)
+                .map(|i| &self[PuzzleIndex::new(i / 9, i % 9)])
+                .filter(|c| !c.is_filled())
+                .collect::<Vec<_>>();
+            let is_false = |c: usize| {
+                let color = &colors[c];
+                combinations(color, 2)
+                    .iter()
+                    .any(|x| x[0].0 == x[1].0 || (x[0].1 == x[1].1 && x[0].0.is_peer(&x[1].0)))
+                    || unfilled.iter().any(|cell| {
+                        !cells.contains(&cell.pos())
+                            && (0..9)
+                                .filter(|k| cell.bit() & (1 << k) != 0)
+                                .all(|k| sees(cell.pos(), k, color))
+                    })
+            };
+            if let Some(c) = (0..2).find(|&c| is_false(c)) {
+                for &(p, k) in &colors[c] {
+                    step.eliminate(&self[p], 1 << k);
+                }
+                ret.push(step);
+                continue;
+            }
+            for cell in &unfilled {
+                let pos = cell.pos();
+                // このマスにある各色の候補
+                let here = [0, 1].map(|c| {
+                    colors[c]
+                        .iter()
+                        .filter(|(p, _)| *p == pos)
+                        .fold(0u16, |acc, (_, k)| acc | 1 << k)
+                });
+                for k in 0..9 {
+                    if cell.bit() & (1 << k) == 0 || (here[0] | here[1]) & (1 << k) != 0 {
+                        continue;
+                    }
+                    // 両方の色から, 見えるか同じマスにいる
+                    let touched = [0, 1].map(|c| here[c] != 0 || sees(pos, k, &colors[c]));
+                    if touched[0] && touched[1] {
+                        step.eliminate(cell, 1 << k);
+                    }
+                }
+            }
+            ret.push(step);
+        }
+        ret
+    }
+}
+
+// 強リンクでつながる要素の集まりごとに, 交互に二色で塗り分ける
+fn paint<T: Copy + PartialEq>(links: &[(T, T)]) -> Vec<[Vec<T>; 2]> {
+    let mut ret: Vec<[Vec<T>; 2]> = vec![];
+    for &(start, _) in links {
+        if ret
+            .iter()
+            .any(|c| c[0].contains(&start) || c[1].contains(&start))
+        {
+            continue;
+        }
+        let mut colors = [vec![start], vec![]];
+        let mut queue = vec![(start, 0)];
+        while let Some((pos, color)) = queue.pop() {
+            for &(a, b) in links {
+                let next = if a == pos {
+                    b
+                } else if b == pos {
+                    a
+                } else {
+                    continue;
+                };
+                if colors[0].contains(&next) || colors[1].contains(&next) {
+                    continue;
+                }
+                colors[1 - color].push(next);
+                queue.push((next, 1 - color));
+            }
+        }
+        ret.push(colors);
+    }
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::puzzle::{assert_finds, parse_candidates};
+    use super::*;
+
+    #[test]
+    fn color_trap() {
+        let puzzle = parse_candidates(
+            "
+             2   4   39   7 6  1 8    39 5
+             579 579 6    8 4  3 79   2  1
+             37  8   1    2 5  9 6    37 4
+             8   379 379  4 39 5 2    1  6
+             6   359 3459 1 2  8 3479 79 39
+             1   2   349  6 39 7 349  5  8
+             579 1   579  3 8  6 59   4  2
+             35  6   2    9 1  4 35   8  7
+             4   39  8    5 7  2 1    6  39
+            ",
+        );
+        assert_finds(&puzzle, TechniqueKind::ColorTrap, "r5c3<>3");
+    }
+
+    #[test]
+    fn multi_coloring() {
+        let puzzle = parse_candidates(
+            "
+             567 3   2  4   167  179 8   567   5679
+             4   1   56 8   367  379 357 2     35679
+             67  9   8  5   2    37  4   367   1
+             2   57  9  137 8    157 6   3457  3457
+             3   8   1  6   57   4   57  9     2
+             56  567 4  37  9    2   1   3578  3578
+             1   56  7  9   345  35  2   34568 34568
+             9   4   56 2   1357 8   357 1357  3567
+             8   2   3  17  1457 6   9   1457  457
+            ",
+        );
+        assert_finds(&puzzle, TechniqueKind::MultiColoring, "r8c7<>5");
+    }
+
+    #[test]
+    fn medusa_3d() {
+        let puzzle = parse_candidates(
+            "
+             4    8  29   256   3    125  69 7 12
+             239  1  2379 8     246  27   69 5 24
+             6    27 5    124   9    127  8  3 124
+             123  47 47   1235  125  6    23 8 9
+             1238 9  238  123   7    1238 4  6 5
+             238  5  6    9     28   4    23 1 7
+             5    3  1489 14    148  189  7  2 6
+             7    24 124  12346 1246 123  5  9 8
+             289  6  289  7     258  2589 1  4 3
+            ",
+        );
+        assert_finds(&puzzle, TechniqueKind::Medusa3D, "r8c6<>2");
+    }
+}
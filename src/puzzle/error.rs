@@ -0,0 +1,53 @@
+use std::fmt::Display;
+
+use super::{PuzzleIndex, SliceType};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SudokuError {
+    // 1..=9以外の数字を入れようとした
+    DigitOutOfRange {
+        pos: PuzzleIndex,
+        digit: u8,
+    },
+    // 同じ行/列/ブロックで同じ数字が確定している
+    DuplicateDigit {
+        slice: SliceType,
+        digit: u8,
+        cells: Vec<PuzzleIndex>,
+    },
+    // 未確定なのに候補がないマス
+    NoCandidates(PuzzleIndex),
+    // 行/列/ブロックのどこにも入らない数字
+    MissingDigit {
+        slice: SliceType,
+        digit: u8,
+    },
+    // 確定した数字が同じ行/列/ブロックの未確定のマスの候補に残っている. cellsは確定したマス, 候補に残っているマスの順
+    CandidateConflict {
+        slice: SliceType,
+        digit: u8,
+        cells: Vec<PuzzleIndex>,
+    },
+}
+
+impl Display for SudokuError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::DigitOutOfRange { pos, digit } => write!(f, "{} cannot be {}", pos, digit),
+            Self::DuplicateDigit { slice, digit, .. } => write!(f, "{} has {} twice", slice, digit),
+            Self::NoCandidates(pos) => write!(f, "{} has no candidate", pos),
+            Self::MissingDigit { slice, digit } => write!(f, "{} has no {}", slice, digit),
+            Self::CandidateConflict {
+                slice,
+                digit,
+                cells,
+            } => write!(
+                f,
+                "{} has {} at {} and as a candidate",
+                slice, digit, cells[0]
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SudokuError {}
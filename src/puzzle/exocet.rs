@@ -0,0 +1,457 @@
+use super::{
+    fish::Orientation, puzzle::combinations, step::TechniqueKind, Puzzle, PuzzleIndex, SliceType,
+    Step,
+};
+
+impl Orientation {
+    // line番目の行(列)のi番目のマス
+    fn at(&self, line: usize, i: usize) -> PuzzleIndex {
+        match self {
+            Self::Row => PuzzleIndex::new(i, line),
+            Self::Col => PuzzleIndex::new(line, i),
+        }
+    }
+}
+
+impl Puzzle {
+    // 同じブロックの同じ行(列)にある二つのマス (ベース) の候補が3,4個で, 同じ段の他の二つのブロックにターゲットのマスがあるとき,
+    // ベースに入る二つの数字はターゲットの二つのマスに入る. 各ベースの数字について,
+    // - ターゲットの列(行)の段の中の残りのマス (コンパニオン) にその数字がない
+    // - ベースのブロックの残りの列(行)とターゲットの列(行)の段の外のマス (S) にあるその数字が二つの行(列)に収まる
+    // ならば, Sには数字が高々二つしか入らず, 三本の列(行)のどれかでは段の中のターゲットに入るからである.
+    // Seniorでは二つのターゲットが同じ行(列)にあってよく, Sを覆う二つの家は行/列/ブロックのどれでもよい.
+    pub(super) fn exocet(&self, technique: TechniqueKind) -> Vec<Step> {
+        let senior = technique == TechniqueKind::SeniorExocet;
+        let mut ret = vec![];
+        for orientation in Orientation::BOTH {
+            for band in 0..3 {
+                let lines = [band * 3, band * 3 + 1, band * 3 + 2];
+                for r0 in lines {
+                    for stack in 0..3 {
+                        let columns = [stack * 3, stack * 3 + 1, stack * 3 + 2];
+                        for (cb1, cb2, c3) in [(0, 1, 2), (0, 2, 1), (1, 2, 0)] {
+                            let base = [
+                                orientation.at(r0, columns[cb1]),
+                                orientation.at(r0, columns[cb2]),
+                            ];
+                            if base.iter().any(|p| self[*p].is_filled()) {
+                                continue;
+                            }
+                            let digits = self[base[0]].bit() | self[base[1]].bit();
+                            if !(3..=4).contains(&digits.count_ones()) {
+                                continue;
+                            }
+                            let cross = columns[c3];
+                            let others = (0..3).filter(|&s| s != stack).collect::<Vec<_>>();
+                            let targets = |s: usize| {
+                                lines
+                                    .iter()
+                                    .filter(|&&r| r != r0)
+                                    .flat_map(|&r| (s * 3..s * 3 + 3).map(move |c| (r, c)))
+                                    .collect::<Vec<_>>()
+                            };
+                            for &(r1, ct1) in &targets(others[0]) {
+                                for &(r2, ct2) in &targets(others[1]) {
+                                    if r1 == r2 && !senior {
+                                        continue;
+                                    }
+                                    let step = self.exocet_step(
+                                        technique,
+                                        orientation,
+                                        (lines, r0),
+                                        base,
+                                        [(r1, ct1), (r2, ct2)],
+                                        cross,
+                                        digits,
+                                    );
+                                    if let Some(step) = step {
+                                        ret.push(step);
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        ret
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn exocet_step(
+        &self,
+        technique: TechniqueKind,
+        orientation: Orientation,
+        (lines, r0): ([usize; 3], usize),
+        base: [PuzzleIndex; 2],
+        targets: [(usize, usize); 2],
+        cross: usize,
+        digits: u16,
+    ) -> Option<Step> {
+        let has = |p: PuzzleIndex, bit: u16| self[p].bit() & bit != 0;
+        let cells = targets.map(|(r, c)| orientation.at(r, c));
+        if cells
+            .iter()
+            .any(|p| self[*p].is_filled() || !has(*p, digits))
+        {
+            return None;
+        }
+        // コンパニオンにはベースの数字が一つもない
+        for (r, c) in targets {
+            let companion = lines.iter().find(|&&l| l != r0 && l != r).unwrap();
+            if has(orientation.at(*companion, c), digits) {
+                return None;
+            }
+        }
+        let s_lines = (0..9).filter(|l| !lines.contains(l)).collect::<Vec<_>>();
+        let mut needs_senior = targets[0].0 == targets[1].0;
+        for k in (0..9).filter(|k| digits & (1 << k) != 0) {
+            let occurrences = s_lines
+                .iter()
+                .flat_map(|&l| [cross, targets[0].1, targets[1].1].map(|c| orientation.at(l, c)))
+                .filter(|p| has(*p, 1 << k))
+                .collect::<Vec<_>>();
+            let mut rows = occurrences.iter().map(|p| p.row()).collect::<Vec<_>>();
+            if let Orientation::Col = orientation {
+                rows = occurrences.iter().map(|p| p.col()).collect();
+            }
+            rows.sort();
+            rows.dedup();
+            if rows.len() <= 2 {
+                continue;
+            }
+            if technique == TechniqueKind::SeniorExocet && self.covered_by_two(&occurrences) {
+                needs_senior = true;
+                continue;
+            }
+            return None;
+        }
+        if needs_senior != (technique == TechniqueKind::SeniorExocet) {
+            return None;
+        }
+        let mut step = Step::new(
+            technique,
+            base.iter().chain(&cells).copied().collect(),
+            digits,
+        );
+        for p in &cells {
+            step.eliminate(&self[*p], !digits);
+        }
+        // どちらのターゲットにも入らない数字はベースにも入らない
+        for k in (0..9).filter(|k| digits & (1 << k) != 0) {
+            if cells.iter().all(|p| !has(*p, 1 << k)) {
+                for p in &base {
+                    step.eliminate(&self[*p], 1 << k);
+                }
+            }
+        }
+        Some(step)
+    }
+
+    // cellsが二つの行/列/ブロックで覆えるか
+    fn covered_by_two(&self, cells: &[PuzzleIndex]) -> bool {
+        let houses = (0..9)
+            .flat_map(|i| [SliceType::Row(i), SliceType::Col(i), SliceType::Block(i)])
+            .collect::<Vec<_>>();
+        combinations(&houses, 2).iter().any(|pair| {
+            cells
+                .iter()
+                .all(|p| pair.iter().any(|h| self.slice(*h).contains(*p)))
+        })
+    }
+
+    // 四つのブロックで, 中心のマスを通る行と列の, 中心以外の二マスずつ (ペア) を八つ輪につなぐ. 隣り合うペアが共有する
+    // 行/列/ブロックごとにその両方に現れる数字の組Xを選び, 各ペアの候補が両隣のXに含まれ, Xの大きさの合計が16なら,
+    // 16マスのそれぞれがどれか一つのXを使い切るので, Xの数字はその行/列/ブロックのペアの外から, 両隣のXに共通する数字はペアから取り除ける.
+    pub(super) fn sk_loop(&self) -> Vec<Step> {
+        let mut ret = vec![];
+        for bands in combinations(&[0, 1, 2], 2) {
+            for stacks in combinations(&[0, 1, 2], 2) {
+                for center in 0..81 {
+                    // 段ごとの中心の行と柱ごとの中心の列
+                    let rows = [bands[0] * 3 + center % 3, bands[1] * 3 + center / 3 % 3];
+                    let cols = [stacks[0] * 3 + center / 9 % 3, stacks[1] * 3 + center / 27];
+                    // 中心の行の, ブロックの中で中心の列以外のマス
+                    let row_pair = |r: usize, c: usize| {
+                        let first = c / 3 * 3;
+                        (first..first + 3)
+                            .filter(|&x| x != c)
+                            .map(|x| PuzzleIndex::new(x, r))
+                            .collect::<Vec<_>>()
+                    };
+                    let col_pair = |r: usize, c: usize| {
+                        let first = r / 3 * 3;
+                        (first..first + 3)
+                            .filter(|&y| y != r)
+                            .map(|y| PuzzleIndex::new(c, y))
+                            .collect::<Vec<_>>()
+                    };
+                    let pairs = [
+                        row_pair(rows[0], cols[0]),
+                        row_pair(rows[0], cols[1]),
+                        col_pair(rows[0], cols[1]),
+                        col_pair(rows[1], cols[1]),
+                        row_pair(rows[1], cols[1]),
+                        row_pair(rows[1], cols[0]),
+                        col_pair(rows[1], cols[0]),
+                        col_pair(rows[0], cols[0]),
+                    ];
+                    if pairs.iter().flatten().any(|p| self[*p].is_filled()) {
+                        continue;
+                    }
+                    // pairs[i]とpairs[i + 1]が共有する行/列/ブロック
+                    let houses = [
+                        SliceType::Row(rows[0]),
+                        SliceType::Block(pairs[1][0].block_idx()),
+                        SliceType::Col(cols[1]),
+                        SliceType::Block(pairs[4][0].block_idx()),
+                        SliceType::Row(rows[1]),
+                        SliceType::Block(pairs[5][0].block_idx()),
+                        SliceType::Col(cols[0]),
+                        SliceType::Block(pairs[0][0].block_idx()),
+                    ];
+                    let candidates = pairs.clone().map(|p| self.union(&p));
+                    let mut links = vec![];
+                    if !sk_links(&candidates, &mut links) {
+                        continue;
+                    }
+                    let cells = pairs.iter().flatten().copied().collect::<Vec<_>>();
+                    let digits = candidates.iter().fold(0, |acc, d| acc | d);
+                    let mut step = Step::new(TechniqueKind::SkLoop, cells.clone(), digits);
+                    for (i, (house, &x)) in houses.iter().zip(&links).enumerate() {
+                        for cell in self.slice(*house).iter() {
+                            if !cells.contains(&cell.pos()) {
+                                step.eliminate(cell, x);
+                            }
+                        }
+                        for p in &pairs[(i + 1) % 8] {
+                            step.eliminate(&self[*p], x & links[(i + 1) % 8]);
+                        }
+                    }
+                    ret.push(step);
+                }
+            }
+        }
+        ret
+    }
+
+    // n本の行とn本の列の交差する未確定のマスSについて, 各数字がSに入りうる回数は, Sの中でその数字を持つ行の数と列の数の小さい方以下.
+    // その合計がSのマスの数に等しければ, どの数字もちょうどその回数だけ入るので, 行の数が小さい数字はその行のSの外から,
+    // 列の数が小さい数字はその列のSの外から取り除ける.
+    pub(super) fn multi_sector_locked_set(&self) -> Vec<Step> {
+        let mut ret = vec![];
+        let all = (0..9).collect::<Vec<_>>();
+        for size in 2..=5 {
+            let lines = combinations(&all, size);
+            for rows in &lines {
+                for cols in &lines {
+                    let cells = rows
+                        .iter()
+                        .flat_map(|&r| cols.iter().map(move |&c| PuzzleIndex::new(c, r)))
+                        .filter(|p| !self[*p].is_filled())
+                        .collect::<Vec<_>>();
+                    // どの行と列もSのマスを二つ以上含む
+                    if rows
+                        .iter()
+                        .any(|&r| cells.iter().filter(|p| p.row() == r).count() < 2)
+                        || cols
+                            .iter()
+                            .any(|&c| cells.iter().filter(|p| p.col() == c).count() < 2)
+                    {
+                        continue;
+                    }
+                    let count = |k: usize, line: fn(&PuzzleIndex) -> usize| {
+                        let mut v = cells
+                            .iter()
+                            .filter(|p| self[**p].bit() & (1 << k) != 0)
+                            .map(line)
+                            .collect::<Vec<_>>();
+                        v.sort();
+                        v.dedup();
+                        v
+                    };
+                    let counts = (0..9)
+                        .map(|k| (count(k, PuzzleIndex::row), count(k, PuzzleIndex::col)))
+                        .collect::<Vec<_>>();
+                    let total = counts
+                        .iter()
+                        .map(|(r, c)| r.len().min(c.len()))
+                        .sum::<usize>();
+                    if total != cells.len() {
+                        continue;
+                    }
+                    let digits = self.union(&cells);
+                    let mut step = Step::new(TechniqueKind::Msls, cells.clone(), digits);
+                    for (k, (r, c)) in counts.iter().enumerate() {
+                        let min = r.len().min(c.len());
+                        let mut targets = vec![];
+                        if r.len() == min {
+                            targets.extend(r.iter().map(|&i| SliceType::Row(i)));
+                        }
+                        if c.len() == min {
+                            targets.extend(c.iter().map(|&i| SliceType::Col(i)));
+                        }
+                        for type_ in targets {
+                            for cell in self.slice(type_).iter() {
+                                if !cells.contains(&cell.pos()) {
+                                    step.eliminate(cell, 1 << k);
+                                }
+                            }
+                        }
+                    }
+                    ret.push(step);
+                }
+            }
+        }
+        ret
+    }
+}
+
+// SK-Loopの各ペアの候補candidatesから, 隣り合うペアの間の数字の組を合計が16になるように選ぶ
+fn sk_links(candidates: &[u16; 8], links: &mut Vec<u16>) -> bool {
+    let common = |i: usize| candidates[i] & candidates[(i + 1) % 8];
+    let i = links.len();
+    if i == 0 {
+        // 各ペアの候補は両隣の組に収まり, 組の数字は合計16なので, ペアの候補の数の合計は32以下
+        let fits = (0..8).all(|i| candidates[i] & !(common((i + 7) % 8) | common(i)) == 0);
+        let total = candidates.iter().map(|c| c.count_ones()).sum::<u32>();
+        if !fits || total > 32 {
+            return false;
+        }
+    }
+    let used = links.iter().map(|x| x.count_ones()).sum::<u32>();
+    if i == 8 {
+        return used == 16 && candidates[0] & !(links[7] | links[0]) == 0;
+    }
+    // i番目より後の組に入りうる数字の数
+    let rest = (i + 1..8).map(|j| common(j).count_ones()).sum::<u32>();
+    // commonの部分集合を大きい順に
+    let common = common(i);
+    let mut x = common;
+    loop {
+        let covered = i == 0 || candidates[i] & !(links[i - 1] | x) == 0;
+        let size = used + x.count_ones();
+        if covered && size <= 16 && size + rest >= 16 {
+            links.push(x);
+            if sk_links(candidates, links) {
+                return true;
+            }
+            links.pop();
+        }
+        if x == 0 {
+            return false;
+        }
+        x = (x - 1) & common;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::puzzle::{assert_finds, parse, parse_candidates};
+    use super::*;
+
+    #[test]
+    fn sk_loop() {
+        // Easter Monster. 最初の候補の状態でSK-Loopがある
+        let puzzle = parse(
+            "1.......2.9.4...5...6...7...5.9.3.......7.......85..4.7.....6...3...9.8...2.....1",
+        );
+        assert_finds(&puzzle, TechniqueKind::SkLoop, "r5c2<>48, r7c3<>1, r9c1<>6, r8c4<>5, r8c5<>4, r5c8<>39, r2c5<>38, r2c6<>8, r1c3<>7, r3c1<>2");
+    }
+
+    #[test]
+    fn sk_loop_gives_up_early_on_empty_grid() {
+        // 候補が多すぎる盤面では組の選び方を探さない
+        let start = std::time::Instant::now();
+        assert!(Puzzle::new().sk_loop().is_empty());
+        assert!(start.elapsed() < std::time::Duration::from_secs(1));
+    }
+
+    #[test]
+    fn multi_sector_locked_set() {
+        // 解の周りに候補を残して作った盤面. 解は一つに定まる
+        let puzzle = parse_candidates(
+            "
+             9      37    137  6    35     4     2   57   8
+             2467   236   36   259  8      1     367 4569 57
+             2468   5     34   7    23     29    46  46   1
+             2468   2378  9    1458 134    5678  67  67   57
+             678    1     28   25   56     78    9   3    4
+             456    3467  37   139  349    3679  157 8    2
+             348    4678  35   58   57     2358  457 1    9
+             123478 2348  48   2359 137    35789 458 27   6
+             145    24679 2578 2458 125679 5678  478 257  3
+            ",
+        );
+        assert_finds(
+            &puzzle,
+            TechniqueKind::Msls,
+            "r3c1<>46, r3c3<>4, r2c7<>6, r2c8<>6, r4c1<>6, r4c6<>67, r4c2<>7, r4c9<>7",
+        );
+    }
+
+    #[test]
+    fn junior_exocet() {
+        // 解の周りに候補を残して作った盤面. 解は一つに定まる
+        let puzzle = parse_candidates(
+            "
+             89    78    12     5     4    278  17    169   3
+             6     13789 124589 123   13   1278 14789 489   14
+             1348  137   178    9     1368 17   78    2     5
+             24589 6     13478  34    138  58   89    13489 12
+             1589  59    38     12368 7    158  1489  348   1268
+             124   18    14     3468  168  9    348   5     7
+             7     2     89     18    5    4    6     1389  19
+             159   58    168    7     89   3    2     149   1489
+             13    4     19     18    2    6    5     7     189
+            ",
+        );
+        assert_finds(
+            &puzzle,
+            TechniqueKind::JuniorExocet,
+            "r3c2<>7, r1c7<>7, r2c4<>2",
+        );
+    }
+
+    #[test]
+    fn junior_exocet_needs_companions_without_base_digits() {
+        // junior_exocetの盤面で, ターゲットr3c2のコンパニオンr1c2にベースの数字1を残したもの
+        let puzzle = parse_candidates(
+            "
+             89    178   12     5     4    278  17    169   3
+             6     13789 124589 123   13   1278 14789 489   14
+             1348  137   178    9     1368 17   78    2     5
+             24589 6     13478  34    138  58   89    13489 12
+             1589  59    38     12368 7    158  1489  348   1268
+             124   18    14     3468  168  9    348   5     7
+             7     2     89     18    5    4    6     1389  19
+             159   58    168    7     89   3    2     149   1489
+             13    4     19     18    2    6    5     7     189
+            ",
+        );
+        assert!(puzzle.find_steps(TechniqueKind::JuniorExocet).is_empty());
+    }
+
+    #[test]
+    fn senior_exocet() {
+        // 解の周りに候補を残して作った盤面. 解は一つに定まる
+        let puzzle = parse_candidates(
+            "
+             146   3    167  468  2    5    68   168   9
+             1249  128  29   148  7    148  38   5     136
+             679   1467 156  3    169  4689 2    1678  467
+             1368  67   236  9    38   367  5    4     13678
+             789   67   1679 5    13   14   39   2     67
+             1679  5    4    126  136  1367 369  3678  1368
+             13    9    137  147  136  2    3478 3678  5
+             34567 247  8    1467 1459 69   67   36    237
+             67    467  367  478  589  389  1    36789 2367
+            ",
+        );
+        assert_finds(
+            &puzzle,
+            TechniqueKind::SeniorExocet,
+            "r3c2<>67, r3c9<>67, r2c4<>8, r2c6<>8",
+        );
+    }
+}
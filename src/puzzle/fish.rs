@@ -0,0 +1,268 @@
+use super::{puzzle::combinations, step::TechniqueKind, Puzzle, PuzzleIndex, SliceType, Step};
+
+// 基底となる行/列の向き. 被覆はその逆の向きになる
+#[derive(Clone, Copy)]
+pub(super) enum Orientation {
+    Row,
+    Col,
+}
+
+impl Orientation {
+    pub(super) const BOTH: [Orientation; 2] = [Self::Col, Self::Row];
+
+    pub(super) fn base(&self, idx: usize) -> SliceType {
+        match self {
+            Self::Row => SliceType::Row(idx),
+            Self::Col => SliceType::Col(idx),
+        }
+    }
+
+    pub(super) fn cover(&self, idx: usize) -> SliceType {
+        match self {
+            Self::Row => SliceType::Col(idx),
+            Self::Col => SliceType::Row(idx),
+        }
+    }
+}
+
+impl Puzzle {
+    // 各数字について, size個の行(列)においてその数字の候補がsize個の列(行)にのみ現れるならば, その列(行)の他のマスから数字を取り除く.
+    pub(super) fn fish(&self, size: usize) -> Vec<Step> {
+        let technique = match size {
+            2 => TechniqueKind::XWing,
+            3 => TechniqueKind::Swordfish,
+            _ => TechniqueKind::Jellyfish,
+        };
+        let mut ret = vec![];
+        for k in 0..9 {
+            for orientation in Orientation::BOTH {
+                // 数字kの候補が2..=size個の位置にだけある基底の行/列
+                let lines = (0..9)
+                    .map(|i| (i, self.slice(orientation.base(i)).to_number_appearance()[k]))
+                    .filter(|(_, a)| (2..=size as u32).contains(&a.count_ones()))
+                    .collect::<Vec<_>>();
+                for lines in combinations(&lines, size) {
+                    let positions = lines.iter().fold(0, |acc, (_, a)| acc | a);
+                    if positions.count_ones() as usize != size {
+                        continue;
+                    }
+                    let base = lines
+                        .iter()
+                        .map(|(i, _)| orientation.base(*i))
+                        .collect::<Vec<_>>();
+                    let cover = (0..9)
+                        .filter(|t| positions & (1 << t) != 0)
+                        .map(|t| orientation.cover(t))
+                        .collect::<Vec<_>>();
+                    ret.push(self.fish_step(technique, k, base, cover));
+                }
+            }
+        }
+        ret
+    }
+
+    // 被覆集合のうち基底集合に含まれないマスから数字kを取り除く手順
+    pub(super) fn fish_step(
+        &self,
+        technique: TechniqueKind,
+        k: usize,
+        base: Vec<SliceType>,
+        cover: Vec<SliceType>,
+    ) -> Step {
+        let cells = base
+            .iter()
+            .flat_map(|b| self.slice(*b).iter().map(|c| c.pos()).collect::<Vec<_>>())
+            .filter(|p| self.has_candidate(*p, k))
+            .collect();
+        let mut step = Step::new(technique, cells, 1 << k);
+        for c in &cover {
+            for cell in self.slice(*c).iter() {
+                if !base.iter().any(|b| self.slice(*b).contains(cell.pos())) {
+                    step.eliminate(cell, 1 << k);
+                }
+            }
+        }
+        step.with_sets(base, cover)
+    }
+
+    // 基底集合の候補のうち被覆集合の外にあるもの (ヒレ) が一つのブロックに収まっていれば, そのブロックと被覆集合の共通部分から数字を取り除ける.
+    // sashimiならば, ヒレを除くと被覆集合の中に候補が一つしかない基底の行/列を含むものだけを返す.
+    pub(super) fn finned_fish(&self, size: usize, sashimi: bool) -> Vec<Step> {
+        let technique = match (size, sashimi) {
+            (2, false) => TechniqueKind::FinnedXWing,
+            (2, true) => TechniqueKind::SashimiXWing,
+            (3, false) => TechniqueKind::FinnedSwordfish,
+            (3, true) => TechniqueKind::SashimiSwordfish,
+            (_, false) => TechniqueKind::FinnedJellyfish,
+            (_, true) => TechniqueKind::SashimiJellyfish,
+        };
+        let mut ret = vec![];
+        for k in 0..9 {
+            for orientation in Orientation::BOTH {
+                let lines = (0..9)
+                    .map(|i| (i, self.candidate_positions(orientation.base(i), k)))
+                    .filter(|(_, a)| *a != 0)
+                    .collect::<Vec<_>>();
+                for lines in combinations(&lines, size) {
+                    let positions = lines.iter().fold(0, |acc, (_, a)| acc | a);
+                    // ヒレは一つのブロックに収まるので, 被覆集合の外の位置は高々3つ
+                    if positions.count_ones() as usize <= size
+                        || positions.count_ones() as usize > size + 3
+                    {
+                        continue;
+                    }
+                    let positions = (0..9)
+                        .filter(|t| positions & (1 << t) != 0)
+                        .collect::<Vec<_>>();
+                    for cover in combinations(&positions, size) {
+                        let cover_mask = cover.iter().fold(0u16, |acc, t| acc | 1 << t);
+                        if lines.iter().any(|(_, a)| a & cover_mask == 0) {
+                            continue;
+                        }
+                        let is_sashimi =
+                            lines.iter().any(|(_, a)| (a & cover_mask).count_ones() < 2);
+                        if is_sashimi != sashimi {
+                            continue;
+                        }
+                        let base = lines
+                            .iter()
+                            .map(|(i, _)| orientation.base(*i))
+                            .collect::<Vec<_>>();
+                        let cover = cover
+                            .iter()
+                            .map(|t| orientation.cover(*t))
+                            .collect::<Vec<_>>();
+                        let fins = base
+                            .iter()
+                            .flat_map(|b| {
+                                self.slice(*b).iter().map(|c| c.pos()).collect::<Vec<_>>()
+                            })
+                            .filter(|p| {
+                                self.has_candidate(*p, k)
+                                    && !cover.iter().any(|c| self.slice(*c).contains(*p))
+                            })
+                            .collect::<Vec<_>>();
+                        let fin_block = fins[0].block_idx();
+                        if fins.iter().any(|p| p.block_idx() != fin_block) {
+                            continue;
+                        }
+                        let mut step = self.fish_step(technique, k, base, cover);
+                        // ヒレと同じブロックにない消去は成り立たない
+                        step.retain_eliminations(|p| p.block_idx() == fin_block);
+                        ret.push(step);
+                    }
+                }
+            }
+        }
+        ret
+    }
+
+    // sliceの中で数字kが未確定の候補として現れる位置
+    pub(super) fn candidate_positions(&self, type_: SliceType, k: usize) -> u16 {
+        self.slice(type_).candidate_positions(k)
+    }
+
+    pub(super) fn has_candidate(&self, pos: PuzzleIndex, k: usize) -> bool {
+        !self[pos].is_filled() && self[pos].bit() & (1 << k) != 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::puzzle::{assert_finds, parse_candidates};
+    use super::*;
+
+    #[test]
+    fn swordfish() {
+        let puzzle = parse_candidates(
+            "
+             237   5  8   367 27  4  12 1267 9
+             247   24 1   567 257 9  3  2467 8
+             23479 6  39  37  1   8  5  247  27
+             8     3  4   2   57  57 6  9    1
+             59    1  59  4   3   6  8  27   27
+             26    7  26  8   9   1  4  3    5
+             246   24 267 9   8   57 12 125  3
+             35    9  35  1   6   2  7  8    4
+             1     8  27  57  4   3  9  25   6
+            ",
+        );
+        assert_finds(
+            &puzzle,
+            TechniqueKind::Swordfish,
+            "r1c1<>2, r1c8<>2, r2c1<>2, r2c8<>2, r7c1<>2, r7c3<>2, r7c8<>2",
+        );
+    }
+
+    #[test]
+    fn jellyfish() {
+        let puzzle = parse_candidates(
+            "
+             189 89 3   7 4  6  129 29 5
+             19  4  6   2 5  3  8   7  19
+             7   2  5   9 1  8  6   4  3
+             4   89 289 5 3  1  29  6  7
+             3   5  29  6 7  29 4   18 18
+             6   1  7   4 8  29 5   3  29
+             28  7  4   3 29 5  129 18 6
+             289 3  89  1 6  4  7   5  28
+             5   6  1   8 29 7  3   29 4
+            ",
+        );
+        assert_finds(&puzzle, TechniqueKind::Jellyfish, "r1c1<>9, r4c3<>9");
+    }
+
+    #[test]
+    fn finned_xwing() {
+        let puzzle = parse_candidates(
+            "
+             1   5  8 3   6   4   7    2   9
+             7   3  4 29  29  1   68   68  5
+             2   6  9 57  578 58  4    3   1
+             3   48 6 1   245 7   9    458 28
+             49  7  2 8   345 59  1    456 36
+             489 1  5 6   234 29  238  48  7
+             458 48 7 245 1   3   2568 9   2468
+             6   9  1 245 28  258 235  7   34
+             458 2  3 79  79  6   58   1   48
+            ",
+        );
+        assert_finds(&puzzle, TechniqueKind::FinnedXWing, "r7c7<>8");
+    }
+
+    #[test]
+    fn sashimi_xwing() {
+        let puzzle = parse_candidates(
+            "
+             36   8    37   1  9    2 5     467   46
+             5    16   17   4  8    3 267   2679  269
+             2    4    9    7  5    6 8     1     3
+             1468 2    18   9  3    7 146   46    5
+             1346 169  34   5  126  8 1246  23469 7
+             7    1369 5    26 126  4 1236  8     1269
+             48   5    248  3  2467 1 9     2467  246
+             9    7    1234 26 246  5 1246  2346  8
+             14   13   6    8  247  9 12347 5     124
+            ",
+        );
+        assert_finds(&puzzle, TechniqueKind::SashimiXWing, "r6c2<>1");
+    }
+
+    #[test]
+    fn finned_swordfish() {
+        let puzzle = parse_candidates(
+            "
+             36   8   37   1  9    2 5     467   46
+             5    16  17   4  8    3 267   2679  269
+             2    4   9    7  5    6 8     1     3
+             1468 2   18   9  3    7 146   46    5
+             1346 169 34   5  126  8 1246  23469 7
+             7    369 5    26 126  4 1236  8     1269
+             48   5   248  3  2467 1 9     2467  246
+             9    7   1234 26 246  5 1246  2346  8
+             14   13  6    8  247  9 12347 5     124
+            ",
+        );
+        assert_finds(&puzzle, TechniqueKind::FinnedSwordfish, "r5c2<>1");
+    }
+}
@@ -0,0 +1,329 @@
+use super::{
+    puzzle::{bits, index, peer_masks},
+    step::TechniqueKind,
+    Puzzle, PuzzleIndex, SliceType, Step,
+};
+
+// 候補を一つ仮定して単独の候補で埋めていった枝. logは仮定から順に埋まったマス
+struct Branch {
+    puzzle: Puzzle,
+    log: Vec<(PuzzleIndex, u8)>,
+    // 矛盾したときの説明
+    contradiction: Option<String>,
+}
+
+impl Branch {
+    // logのi番目までを "r1c1=5 => r2c3=4" のように書く
+    fn describe(&self, i: usize) -> String {
+        self.log[..=i]
+            .iter()
+            .map(|(p, v)| format!("{}={}", p, v))
+            .collect::<Vec<_>>()
+            .join(" => ")
+    }
+
+    fn describe_contradiction(&self) -> String {
+        format!(
+            "{} => {}",
+            self.describe(self.log.len() - 1),
+            self.contradiction.as_deref().unwrap_or_default()
+        )
+    }
+
+    // posに数字kが入らなくなった最初の記録
+    fn eliminated_at(&self, pos: PuzzleIndex, k: u8) -> Option<usize> {
+        self.log
+            .iter()
+            .position(|&(p, v)| (p == pos && v != k) || (v == k && p.is_peer(&pos)))
+    }
+}
+
+fn slices() -> impl Iterator<Item = SliceType> {
+    (0..9).flat_map(|i| [SliceType::Row(i), SliceType::Col(i), SliceType::Block(i)])
+}
+
+impl Puzzle {
+    // posにvを入れ, 矛盾するか単独の候補がなくなるまで埋めていく.
+    // 候補が一つになったマスはfillのpropagateで埋まり, 行/列/ブロックで一か所にしか入らない数字はここで埋める.
+    fn assume(&self, pos: PuzzleIndex, v: u8) -> Branch {
+        let mut branch = Branch {
+            puzzle: self.clone(),
+            log: vec![],
+            contradiction: None,
+        };
+        let mut next = Some((pos, v));
+        while let Some((pos, v)) = next.take() {
+            let before = (0..81)
+                .map(|i| branch.puzzle[index(i)].is_filled())
+                .collect::<Vec<_>>();
+            let filled = branch.puzzle.fill(pos, v);
+            branch.log.push((pos, v));
+            // propagateで続けて埋まったマス
+            for i in (0..81).filter(|&i| !before[i] && index(i) != pos) {
+                let cell = &branch.puzzle[index(i)];
+                if cell.is_filled() {
+                    branch
+                        .log
+                        .push((cell.pos(), cell.to_determined_number().unwrap()));
+                }
+            }
+            branch.contradiction = filled
+                .and_then(|_| branch.puzzle.validate())
+                .err()
+                .map(|e| e.to_string());
+            if branch.contradiction.is_some() {
+                break;
+            }
+            next = slices().find_map(|type_| {
+                let slice = branch.puzzle.slice(type_);
+                (0..9).find_map(|k| {
+                    let positions = slice.candidate_positions(k);
+                    (positions.count_ones() == 1).then(|| {
+                        (
+                            slice[positions.trailing_zeros() as usize].pos(),
+                            k as u8 + 1,
+                        )
+                    })
+                })
+            });
+        }
+        branch
+    }
+
+    // 数字kを一つのマスに仮定し, kだけを考えて行/列/ブロックで一か所にしか入らないkを埋めていく.
+    // どこかの行/列/ブロックにkが入らなくなれば, 仮定したマスからkを取り除く.
+    pub(super) fn nishio(&self) -> Vec<Step> {
+        let peers = peer_masks();
+        let units = slices()
+            .map(|type_| {
+                let slice = self.slice(type_);
+                let mask = slice
+                    .iter()
+                    .fold(0u128, |acc, c| acc | 1 << (c.col() * 9 + c.row()));
+                (type_, mask)
+            })
+            .collect::<Vec<_>>();
+        let candidates = self.candidate_masks();
+        let mut ret = vec![];
+        for (k, &mask) in candidates.iter().enumerate() {
+            let placed = (0..81)
+                .filter(|&i| self[index(i)].is_filled() && self[index(i)].bit() == 1 << k)
+                .fold(0u128, |acc, i| acc | 1 << i);
+            for start in bits(mask) {
+                let (mut placed, mut cand) = (placed, mask);
+                let mut log = vec![];
+                let mut next = Some(start);
+                let mut contradiction = None;
+                while let Some(i) = next.take() {
+                    placed |= 1 << i;
+                    cand &= !(peers[i] | 1 << i);
+                    log.push(format!("{}={}", index(i), k + 1));
+                    for &(type_, mask) in &units {
+                        if placed & mask != 0 {
+                            continue;
+                        }
+                        match (cand & mask).count_ones() {
+                            0 => {
+                                contradiction = Some(format!("{} has no {}", type_, k + 1));
+                                break;
+                            }
+                            1 if next.is_none() => {
+                                next = Some((cand & mask).trailing_zeros() as usize);
+                            }
+                            _ => {}
+                        }
+                    }
+                    if contradiction.is_some() {
+                        break;
+                    }
+                }
+                if let Some(contradiction) = contradiction {
+                    log.push(contradiction);
+                    let mut step = Step::new(TechniqueKind::Nishio, vec![index(start)], 1 << k);
+                    step.eliminate(&self[index(start)], 1 << k);
+                    ret.push(step.with_branches(vec![log.join(" => ")]));
+                }
+            }
+        }
+        ret
+    }
+
+    // 一つのマスの各候補を仮定した枝
+    pub(super) fn cell_forcing_chains(&self) -> Vec<Step> {
+        let mut ret = vec![];
+        for i in 0..81 {
+            let cell = &self[index(i)];
+            if cell.is_filled() || cell.bit().count_ones() < 2 {
+                continue;
+            }
+            let assumptions = (0..9)
+                .filter(|k| cell.bit() & (1 << k) != 0)
+                .map(|k| (cell.pos(), k as u8 + 1))
+                .collect::<Vec<_>>();
+            ret.extend(self.forcing_chain(
+                TechniqueKind::CellForcingChain,
+                vec![cell.pos()],
+                cell.bit(),
+                &assumptions,
+            ));
+        }
+        ret
+    }
+
+    // 一つの行/列/ブロックで数字kが入りうる各マスを仮定した枝
+    pub(super) fn unit_forcing_chains(&self) -> Vec<Step> {
+        let mut ret = vec![];
+        for type_ in slices() {
+            let slice = self.slice(type_);
+            for k in 0..9 {
+                let positions = slice.candidate_positions(k);
+                if positions.count_ones() < 2 {
+                    continue;
+                }
+                let assumptions = (0..9)
+                    .filter(|i| positions & (1 << i) != 0)
+                    .map(|i| (slice[i].pos(), k as u8 + 1))
+                    .collect::<Vec<_>>();
+                let cells = assumptions.iter().map(|(p, _)| *p).collect();
+                ret.extend(self.forcing_chain(
+                    TechniqueKind::UnitForcingChain,
+                    cells,
+                    1 << k,
+                    &assumptions,
+                ));
+            }
+        }
+        ret
+    }
+
+    // assumptionsのどれかは必ず真となる. 矛盾する枝の仮定は偽で, そうでなければすべての枝で一致する結論は真.
+    // 結論ごとに, それを導いた各枝を添えた手順を返す.
+    fn forcing_chain(
+        &self,
+        technique: TechniqueKind,
+        cells: Vec<PuzzleIndex>,
+        digits: u16,
+        assumptions: &[(PuzzleIndex, u8)],
+    ) -> Vec<Step> {
+        let branches = assumptions
+            .iter()
+            .map(|&(pos, v)| self.assume(pos, v))
+            .collect::<Vec<_>>();
+        let mut ret = vec![];
+        for (branch, &(pos, v)) in branches.iter().zip(assumptions) {
+            if branch.contradiction.is_some() {
+                let mut step = Step::new(technique, cells.clone(), digits);
+                step.eliminate(&self[pos], 1 << (v - 1));
+                ret.push(step.with_branches(vec![branch.describe_contradiction()]));
+            }
+        }
+        if !ret.is_empty() {
+            return ret;
+        }
+        for i in 0..81 {
+            let cell = &self[index(i)];
+            if cell.is_filled() {
+                continue;
+            }
+            let pos = cell.pos();
+            // すべての枝で同じ数字が入る
+            let values = branches
+                .iter()
+                .map(|b| {
+                    b.puzzle[pos]
+                        .to_determined_number()
+                        .filter(|_| b.puzzle[pos].is_filled())
+                })
+                .collect::<Vec<_>>();
+            if let Some(v) = values[0].filter(|v| values.iter().all(|w| *w == Some(*v))) {
+                let mut step = Step::new(technique, cells.clone(), digits);
+                step.place(cell, v);
+                let described = branches
+                    .iter()
+                    .map(|b| b.describe(b.log.iter().position(|(p, _)| *p == pos).unwrap()))
+                    .collect();
+                ret.push(step.with_branches(described));
+                continue;
+            }
+            // すべての枝で入らなくなる候補
+            for k in (1..=9).filter(|k| cell.bit() & (1 << (k - 1)) != 0) {
+                let found = branches
+                    .iter()
+                    .map(|b| b.eliminated_at(pos, k))
+                    .collect::<Option<Vec<_>>>();
+                if let Some(found) = found {
+                    let mut step = Step::new(technique, cells.clone(), digits);
+                    step.eliminate(cell, 1 << (k - 1));
+                    let described = branches
+                        .iter()
+                        .zip(found)
+                        .map(|(b, i)| b.describe(i))
+                        .collect();
+                    ret.push(step.with_branches(described));
+                }
+            }
+        }
+        ret
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::puzzle::{assert_finds, parse_candidates};
+    use super::*;
+
+    #[test]
+    fn nishio() {
+        let puzzle = parse_candidates(
+            "
+             57 4   29 25  8    1  3   6   279
+             3  1   6  4   259  27 8   59  279
+             57 29  8  3   2579 6  159 127 4
+             1  8   4  257 57   3  279 29  6
+             9  27  5  6   1    27 4   8   3
+             6  237 23 9   4    8  57  257 1
+             4  39  39 18  27   5  6   127 278
+             2  5   7  18  6    4  19  3   89
+             8  6   1  27  3    9  27  4   5
+            ",
+        );
+        assert_finds(&puzzle, TechniqueKind::Nishio, "r2c6<>2");
+    }
+
+    #[test]
+    fn cell_forcing_chain() {
+        let puzzle = parse_candidates(
+            "
+             6   234  234  358   2589 7   1    459  358
+             8   37   1    3459  59   6   4579 2    35
+             9   2347 5    1     28   34  467  3478 368
+             157 3456 3479 35679 69   2   8    145  135
+             15  68   689  689   4    35  2    13   7
+             2   3458 3478 378   18   13  45   6    9
+             57  1    67   2     3    8   679  579  4
+             4   2568 268  56    7    9   3    158  1568
+             3   9    678  456   156  145 567  578  2
+            ",
+        );
+        assert_finds(&puzzle, TechniqueKind::CellForcingChain, "r1c4<>5");
+    }
+
+    #[test]
+    fn unit_forcing_chain() {
+        // 1列目の4がr5c1, r6c1, r7c1, r9c1のどれに入っても, r6c1は4でない
+        let puzzle = parse_candidates(
+            "
+             1   5  8 3    6     4   7    2   9
+             7   3  4 29   289   1   68   68  5
+             2   6  9 57   578   58  4    3   1
+             3   48 6 1    245   7   9    458 248
+             49  7  2 8    3459  59  1    456 346
+             489 1  5 6    2349  29  238  48  7
+             458 48 7 245  1     3   2568 9   2468
+             6   9  1 245  2458  258 2358 7   2348
+             458 2  3 4579 45789 6   58   1   48
+            ",
+        );
+        assert_finds(&puzzle, TechniqueKind::UnitForcingChain, "r6c1<>4");
+    }
+}
@@ -0,0 +1,70 @@
+use super::{puzzle::index, Puzzle, PuzzleIndex};
+
+// 解と比べて見つかった誤り
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Mistakes {
+    // 解と違う数字が入ったマスと, 入っている数字
+    wrong_digits: Vec<(PuzzleIndex, u8)>,
+    // 解の数字が候補から消えたマスと, 解の数字
+    lost_candidates: Vec<(PuzzleIndex, u8)>,
+}
+
+impl Mistakes {
+    pub fn wrong_digits(&self) -> &[(PuzzleIndex, u8)] {
+        &self.wrong_digits
+    }
+
+    pub fn lost_candidates(&self) -> &[(PuzzleIndex, u8)] {
+        &self.lost_candidates
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.wrong_digits.is_empty() && self.lost_candidates.is_empty()
+    }
+}
+
+impl Puzzle {
+    // 確定した数字と候補をsolutionと比べる. solutionは問題をsolve_by_backtrackingで埋めたものなど.
+    // solutionで確定していないマスは調べない.
+    pub fn mistakes(&self, solution: &Puzzle) -> Mistakes {
+        let mut ret = Mistakes::default();
+        for i in 0..81 {
+            let pos = index(i);
+            if !solution[pos].is_filled() {
+                continue;
+            }
+            let bit = solution[pos].bit();
+            let cell = &self[pos];
+            if cell.is_filled() && cell.bit() != bit {
+                ret.wrong_digits
+                    .push((pos, cell.bit().trailing_zeros() as u8 + 1));
+            } else if !cell.is_filled() && cell.bit() & bit == 0 {
+                ret.lost_candidates
+                    .push((pos, bit.trailing_zeros() as u8 + 1));
+            }
+        }
+        ret
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::puzzle::parse;
+    use super::*;
+
+    #[test]
+    fn finds_wrong_digit_and_lost_candidate() {
+        let solution = parse(
+            "534678912672195348198342567859761423426853791713924856961537284287419635345286179",
+        );
+        let mut puzzle = Puzzle::new();
+        assert!(puzzle.mistakes(&solution).is_empty());
+
+        // r1c3の解は4, r1c4の解は6
+        puzzle[index(2)].insert(1);
+        puzzle[index(3)] -= 1u16 << 5;
+        let mistakes = puzzle.mistakes(&solution);
+        assert_eq!(mistakes.wrong_digits(), [(index(2), 1)]);
+        assert_eq!(mistakes.lost_candidates(), [(index(3), 6)]);
+    }
+}
@@ -1,6 +1,29 @@
 mod puzzle_index;
 pub use puzzle_index::PuzzleIndex;
+#[allow(clippy::module_inception)]
 mod puzzle;
 pub use puzzle::Puzzle;
 mod puzzle_slice;
-pub use puzzle_slice::PuzzleSliceMut;
\ No newline at end of file
+pub use puzzle_slice::{PuzzleSliceMut, SliceType};
+mod als;
+mod backtrack;
+pub use backtrack::Uniqueness;
+mod chain;
+mod coloring;
+mod error;
+pub use error::SudokuError;
+mod exocet;
+mod fish;
+mod forcing;
+mod mistakes;
+pub use mistakes::Mistakes;
+mod single_digit;
+mod solver;
+pub use solver::{SolveReport, Solver, StopReason, Strategy, Technique};
+mod step;
+mod sue_de_coq;
+mod uniqueness;
+mod validation;
+pub use validation::{Rule, ValidationReport, Violation};
+mod wing;
+pub use step::{Step, TechniqueKind};
\ No newline at end of file
@@ -1,13 +1,12 @@
-use std::{
-    collections::HashSet,
-    ops::{Index, IndexMut},
-};
+use std::ops::{Index, IndexMut};
 
 use crate::cell::Cell;
 
 use super::{
+    chain::MAX_CHAIN_LENGTH,
     puzzle_slice::{PuzzleSlice, SliceType},
-    PuzzleIndex, PuzzleSliceMut,
+    step::TechniqueKind,
+    PuzzleIndex, PuzzleSliceMut, Solver, Step, Strategy, SudokuError,
 };
 
 #[derive(Clone)]
@@ -17,520 +16,485 @@ pub struct Puzzle {
 
 impl Puzzle {
     pub fn new() -> Self {
-        let mut inner = vec![vec![]; 9];
-        for i in 0..9 {
-            for j in 0..9 {
-                inner[i].push(Cell::unfilled(PuzzleIndex::new(i, j)));
+        let inner = (0..9)
+            .map(|i| {
+                (0..9)
+                    .map(|j| Cell::unfilled(PuzzleIndex::new(i, j)))
+                    .collect()
+            })
+            .collect();
+        Puzzle { inner }
+    }
+
+    // ヒントclues (マス (col * 9 + row) ごとの数字で, 0はヒントなし) だけを入れた盤面. cluesは矛盾しないものとする.
+    // fillと違い, 候補が一つになったマスも確定させずに残す
+    pub fn from_clues(clues: &[u8; 81]) -> Self {
+        let mut puzzle = Self::new();
+        let givens = (0..81)
+            .filter(|&i| clues[i] != 0)
+            .map(|i| (PuzzleIndex::new(i / 9, i % 9), clues[i]))
+            .collect::<Vec<_>>();
+        for &(pos, v) in &givens {
+            puzzle[pos].insert(v);
+        }
+        for &(pos, v) in &givens {
+            for peer in puzzle.peers(pos).collect::<Vec<_>>() {
+                puzzle[peer] -= 1u16 << (v - 1);
             }
         }
-        Puzzle { inner }
+        puzzle
     }
 
-    pub fn fill(&mut self, pos: PuzzleIndex, v: u8) {
-        if !self[pos].insert(v) {
-            return;
-        };
-        self.propagate(pos);
+    // 同じ行/列/ブロックに確定したvがあるときは入れない. 連鎖して確定したマスで矛盾が起きたときは,
+    // そこまで盤面を変えたままエラーを返す.
+    pub fn fill(&mut self, pos: PuzzleIndex, v: u8) -> Result<(), SudokuError> {
+        if !(1..=9).contains(&v) {
+            return Err(SudokuError::DigitOutOfRange { pos, digit: v });
+        }
+        if self[pos].is_filled() {
+            return Ok(());
+        }
+        for type_ in [
+            SliceType::Row(pos.row()),
+            SliceType::Col(pos.col()),
+            SliceType::Block(pos.block_idx()),
+        ] {
+            if let Some(cell) = self
+                .slice(type_)
+                .iter()
+                .find(|c| c.is_filled() && c.bit() == 1 << (v - 1))
+            {
+                return Err(SudokuError::DuplicateDigit {
+                    slice: type_,
+                    digit: v,
+                    cells: vec![cell.pos(), pos],
+                });
+            }
+        }
+        self[pos].insert(v);
+        self.propagate(pos)
     }
 
-    pub(super) fn determine(&mut self, pos: PuzzleIndex) {
+    pub(super) fn determine(&mut self, pos: PuzzleIndex) -> Result<(), SudokuError> {
         if self[pos].determine() {
-            self.propagate(pos);
+            self.propagate(pos)?;
         }
+        Ok(())
     }
 
-    fn propagate(&mut self, pos: PuzzleIndex) -> bool {
+    // posに確定した数字を同じ行/列/ブロックの候補から消し, 候補が一つになったマスを確定させていく
+    fn propagate(&mut self, pos: PuzzleIndex) -> Result<(), SudokuError> {
         if !self[pos].is_filled() {
-            return false;
+            return Ok(());
         }
         let mut determined = vec![];
         let bit = self[pos].bit();
-        let mut col = self.col_mut(pos.col());
-        for i in 0..9 {
-            col[i] -= bit;
-            if col[i].determine() {
-                determined.push(col[i].pos());
-            }
-        }
-        let mut row = self.row_mut(pos.row());
-        for i in 0..9 {
-            row[i] -= bit;
-            if row[i].determine() {
-                determined.push(row[i].pos());
+        let digit = bit.trailing_zeros() as u8 + 1;
+        for type_ in [
+            SliceType::Col(pos.col()),
+            SliceType::Row(pos.row()),
+            SliceType::Block(pos.block_idx()),
+        ] {
+            let mut slice = self.slice_mut(type_);
+            for i in 0..9 {
+                if slice[i].pos() == pos {
+                    continue;
+                }
+                if slice[i].is_filled() && slice[i].bit() == bit {
+                    return Err(SudokuError::DuplicateDigit {
+                        slice: type_,
+                        digit,
+                        cells: vec![pos, slice[i].pos()],
+                    });
+                }
+                slice[i] -= bit;
+                if !slice[i].is_filled() && slice[i].bit() == 0 {
+                    return Err(SudokuError::NoCandidates(slice[i].pos()));
+                }
+                if slice[i].determine() {
+                    determined.push(slice[i].pos());
+                }
             }
         }
-        let mut block = self.block_mut(pos.block_idx());
-        for i in 0..9 {
-            block[i] -= bit;
-            if block[i].determine() {
-                determined.push(block[i].pos());
-            }
+        for c in determined {
+            self.propagate(c)?;
         }
-        determined.into_iter().for_each(|c| {
-            self.propagate(c);
-        });
-        true
+        Ok(())
     }
 
-    fn col(&self, idx: usize) -> PuzzleSlice {
+    pub(super) fn col(&self, idx: usize) -> PuzzleSlice<'_> {
         PuzzleSlice::new(self, SliceType::Col(idx))
     }
 
-    fn row(&self, idx: usize) -> PuzzleSlice {
+    pub(super) fn row(&self, idx: usize) -> PuzzleSlice<'_> {
         PuzzleSlice::new(self, SliceType::Row(idx))
     }
 
-    fn block(&self, idx: usize) -> PuzzleSlice {
+    pub(super) fn block(&self, idx: usize) -> PuzzleSlice<'_> {
         PuzzleSlice::new(self, SliceType::Block(idx))
     }
 
-    fn col_mut(&mut self, idx: usize) -> PuzzleSliceMut {
-        PuzzleSliceMut::new(self, SliceType::Col(idx))
+    pub(super) fn slice(&self, type_: SliceType) -> PuzzleSlice<'_> {
+        PuzzleSlice::new(self, type_)
     }
 
-    fn row_mut(&mut self, idx: usize) -> PuzzleSliceMut {
-        PuzzleSliceMut::new(self, SliceType::Row(idx))
+    pub(super) fn slice_mut(&mut self, type_: SliceType) -> PuzzleSliceMut<'_> {
+        PuzzleSliceMut::new(self, type_)
     }
 
-    fn block_mut(&mut self, idx: usize) -> PuzzleSliceMut {
-        PuzzleSliceMut::new(self, SliceType::Block(idx))
+    pub fn is_solved(&self) -> bool {
+        self.inner.iter().flatten().all(|c| c.is_filled())
     }
 
-    pub fn validate(&self) -> bool {
-        fn check(s: PuzzleSlice) -> bool {
-            // 同じ行/列/ブロックにおいてfilledが同じ値を持つことがない.
-            if s.iter().filter(|c| c.is_filled()).count()
-                != s.iter()
-                    .filter(|c| c.is_filled())
-                    .map(|c| c.bit() as usize)
-                    .sum::<usize>()
-                    .count_ones() as usize
-            {
-                println!(
-                    "{:?} filledが同じ値を持っている {:b}",
-                    s.type_,
-                    s.iter()
-                        .filter(|c| c.is_filled())
-                        .map(|c| c.bit() as usize)
-                        .sum::<usize>()
-                );
-                return false;
-            }
-            // 同じ行/列/ブロックにおいて, すべてのビット和をとると0b111111111となる.
-            if s.iter().map(|c| c.bit()).fold(0, |i, b| i | b) != 0b111111111 {
-                println!(
-                    "{:?} ビット和が0b111111111とならない {:b}",
-                    s.type_,
-                    s.iter().map(|c| c.bit()).fold(0, |i, b| i | b)
-                );
-                return false;
+    // 候補のビット列のFNV-1aハッシュ. 盤面が変わったかを文字列を作らずに調べる
+    pub fn fingerprint(&self) -> u64 {
+        self.inner
+            .iter()
+            .flatten()
+            .fold(0xcbf29ce484222325, |h, c| {
+                let v = c.bit() as u64 | (c.is_filled() as u64) << 9;
+                (h ^ v).wrapping_mul(0x100000001b3)
+            })
+    }
+
+    // stepのうちまだ効果のある部分を盤面に適用する. 何か変化があればtrue.
+    // 候補がなくなるマスや, 入れられない数字があれば, そこで止めてエラーを返す
+    pub fn apply(&mut self, step: &Step) -> Result<bool, SudokuError> {
+        let mut changed = false;
+        for &(pos, bit) in step.eliminations() {
+            if !self[pos].is_filled() && self[pos].bit() & bit != 0 {
+                self[pos] -= bit;
+                changed = true;
+                if self[pos].bit() == 0 {
+                    return Err(SudokuError::NoCandidates(pos));
+                }
             }
-            // 同じ行/列/ブロックにおいて, filledとunfilledのビット和のビット和は0
-            if s.iter()
-                .filter(|c| c.is_filled())
-                .map(|c| c.bit())
-                .fold(0, |i, b| i | b)
-                & s.iter()
-                    .filter(|c| !c.is_filled())
-                    .map(|c| c.bit())
-                    .fold(0, |i, b| i | b)
-                != 0
-            {
-                println!(
-                    "{:?} filledとunfilledが重なっている {}",
-                    s.type_,
-                    s.iter()
-                        .filter(|c| c.is_filled())
-                        .map(|c| c.bit())
-                        .fold(0, |i, b| i | b)
-                        & s.iter()
-                            .filter(|c| !c.is_filled())
-                            .map(|c| c.bit())
-                            .fold(0, |i, b| i | b)
-                );
-                return false;
+        }
+        for &(pos, value) in step.placements() {
+            if !self[pos].is_filled() {
+                self.fill(pos, value)?;
+                changed |= self[pos].is_filled();
             }
-            true
         }
-        (0..9)
-            .map(|i| self.block(i))
-            .chain((0..9).map(|i| self.col(i)))
-            .chain((0..9).map(|i| self.row(i)))
-            .map(|rcb| check(rcb))
-            .all(|b| b)
+        Ok(changed)
     }
 
-    pub fn hash(&self) -> String {
-        let mut ans = String::new();
-        for i in 0..9 {
-            for j in 0..9 {
-                let idx = PuzzleIndex::new(i, j);
-                ans += self[idx].bit().to_string().as_str();
-                ans.push('/');
-            }
-        }
-        ans.pop();
-        ans
+    pub(super) fn find_steps(&self, technique: TechniqueKind) -> Vec<Step> {
+        self.find_steps_within(technique, MAX_CHAIN_LENGTH)
     }
 
-    fn naked_single(&mut self) {
-        for i in 0..9 {
-            for j in 0..9 {
-                self.determine(PuzzleIndex::new(i, j));
+    // 鎖を使う解法では, 節点の数がmax_chain_length以下の鎖だけを探す
+    pub(super) fn find_steps_within(
+        &self,
+        technique: TechniqueKind,
+        max_chain_length: usize,
+    ) -> Vec<Step> {
+        let steps = match technique {
+            TechniqueKind::NakedSingle => {
+                (0..9).flat_map(|i| self.block(i).naked_subset(1)).collect()
             }
+            TechniqueKind::HiddenSingle => self.for_each_slice(|s| s.hidden_subset(1)),
+            TechniqueKind::Pointing | TechniqueKind::Claiming => self.box_line_reduction(technique),
+            TechniqueKind::NakedPair => self.for_each_slice(|s| s.naked_subset(2)),
+            TechniqueKind::NakedTriple => self.for_each_slice(|s| s.naked_subset(3)),
+            TechniqueKind::HiddenPair => self.for_each_slice(|s| s.hidden_subset(2)),
+            TechniqueKind::HiddenTriple => self.for_each_slice(|s| s.hidden_subset(3)),
+            TechniqueKind::NakedQuad => self.for_each_slice(|s| s.naked_subset(4)),
+            TechniqueKind::HiddenQuad => self.for_each_slice(|s| s.hidden_subset(4)),
+            TechniqueKind::XWing => self.fish(2),
+            TechniqueKind::Swordfish => self.fish(3),
+            TechniqueKind::Skyscraper
+            | TechniqueKind::TwoStringKite
+            | TechniqueKind::TurbotFish => self.turbot_fish(technique),
+            TechniqueKind::EmptyRectangle => self.empty_rectangle(),
+            TechniqueKind::XYWing => self.xy_wing(),
+            TechniqueKind::XYZWing => self.xyz_wing(),
+            TechniqueKind::UniqueRectangle1
+            | TechniqueKind::UniqueRectangle2
+            | TechniqueKind::UniqueRectangle3
+            | TechniqueKind::UniqueRectangle4
+            | TechniqueKind::UniqueRectangle5
+            | TechniqueKind::UniqueRectangle6
+            | TechniqueKind::HiddenRectangle => self.unique_rectangle(technique),
+            TechniqueKind::BugPlusOne => self.bug_plus_one(),
+            TechniqueKind::WXYZWing => self.wxyz_wing(),
+            TechniqueKind::Jellyfish => self.fish(4),
+            TechniqueKind::ColorTrap | TechniqueKind::ColorWrap => self.simple_coloring(technique),
+            TechniqueKind::MultiColoring => self.multi_coloring(),
+            TechniqueKind::Medusa3D => self.medusa(),
+            TechniqueKind::FinnedXWing => self.finned_fish(2, false),
+            TechniqueKind::SashimiXWing => self.finned_fish(2, true),
+            TechniqueKind::FinnedSwordfish => self.finned_fish(3, false),
+            TechniqueKind::SashimiSwordfish => self.finned_fish(3, true),
+            TechniqueKind::FinnedJellyfish => self.finned_fish(4, false),
+            TechniqueKind::SashimiJellyfish => self.finned_fish(4, true),
+            TechniqueKind::SueDeCoq => self.sue_de_coq(),
+            TechniqueKind::XChain
+            | TechniqueKind::XYChain
+            | TechniqueKind::XCycle
+            | TechniqueKind::AIC
+            | TechniqueKind::GroupedAIC => self.chains(technique, max_chain_length),
+            TechniqueKind::AlsXz => self.als_xz(),
+            TechniqueKind::AlsXyWing => self.als_xy_wing(),
+            TechniqueKind::DeathBlossom => self.death_blossom(),
+            TechniqueKind::Nishio => self.nishio(),
+            TechniqueKind::CellForcingChain => self.cell_forcing_chains(),
+            TechniqueKind::UnitForcingChain => self.unit_forcing_chains(),
+            TechniqueKind::SkLoop => self.sk_loop(),
+            TechniqueKind::Msls => self.multi_sector_locked_set(),
+            TechniqueKind::JuniorExocet | TechniqueKind::SeniorExocet => self.exocet(technique),
+        };
+        steps.into_iter().filter(|s| !s.is_empty()).collect()
+    }
+
+    // 各行/列/ブロックについてfを適用する
+    fn for_each_slice(&self, f: impl Fn(&PuzzleSlice) -> Vec<Step>) -> Vec<Step> {
+        (0..9)
+            .flat_map(|i| [self.block(i), self.col(i), self.row(i)])
+            .flat_map(|s| f(&s))
+            .collect()
+    }
+
+    // 基本的な解法 (TechniqueKind::BASIC) を順に一度ずつ使う. 使う解法や順番を選ぶにはSolverを使う. 盤面が変わればtrue.
+    // 手順を適用して矛盾が出たら, 盤面をvalidateして最初に見つかった矛盾を返す
+    pub fn solve(&mut self) -> Result<bool, SudokuError> {
+        let before = self.fingerprint();
+        if let Err(e) = Solver::new().strategy(Strategy::SinglePass).solve(self) {
+            self.validate()?;
+            return Err(e);
         }
+        Ok(self.fingerprint() != before)
     }
 
-    pub fn solve(&mut self) {
-        self.naked_single();
-        self.hidden_single();
-        self.box_line_reduction();
-        self.naked_pair();
-        self.naked_triple();
-        self.hidden_pair();
-        self.hidden_triple();
-        self.x_wing();
-        self.xy_wing();
+    // solveを盤面が変わらなくなるまで繰り返す
+    pub fn solve_to_fixpoint(&mut self) -> Result<(), SudokuError> {
+        while self.solve()? {}
+        Ok(())
     }
 
-    fn x_wing(&mut self) {
-        // 各数字について, ある二つの行/列が存在して, その行/列において数字が同じ二つの位置にのみ存在していた場合, その二つの位置の属する列/行からその数字を取り除く.
-        for i in 0..9 {
-            for j in i + 1..9 {
-                let tar = self.col(i).to_number_appearance();
-                let tar2 = self.col(j).to_number_appearance();
-                for k in 0..9 {
-                    if tar[k].count_ones() != 2 {
-                        continue;
-                    }
-                    if tar2[k] != tar[k] {
-                        continue;
-                    }
-                    for t in 0..9 {
-                        if (1 << t) & tar[k] == 0 {
-                            continue;
-                        }
-                        // t列の他のセルからkを取り除く.
-                        let mut row = self.row_mut(t);
-                        for l in 0..9 {
-                            if l == i || l == j {
-                                continue;
-                            }
-                            row[t] -= 1u16 << k;
-                        }
+    // 盤面は変えずに, 基本的な解法 (TechniqueKind::BASIC) のうち最も簡単なもので見つかる手順を一つ返す.
+    // より難しい解法も使うには, Solver::techniquesで選んでからSolver::next_hintを使う
+    pub fn next_hint(&self) -> Option<Step> {
+        Solver::new().next_hint(self)
+    }
+
+    // solveを変化がなくなるまで繰り返し, 実際に盤面を変えた手順を順に返す. 解が一つと仮定する解法は使わない.
+    // 手順を適用して矛盾が出たらそこで止めてエラーを返す
+    pub fn solve_with_trace(&mut self) -> Result<Vec<Step>, SudokuError> {
+        let mut trace = vec![];
+        while {
+            let len = trace.len();
+            for technique in TechniqueKind::ALL
+                .into_iter()
+                .filter(|t| !t.requires_uniqueness())
+            {
+                for step in self.find_steps(technique) {
+                    // 先に適用した手順で既に消えた候補は記録しない
+                    let step = step.pruned(self);
+                    if self.apply(&step)? {
+                        trace.push(step);
                     }
                 }
-                let tar = self.row(i).to_number_appearance();
-                let tar2 = self.row(j).to_number_appearance();
-                for k in 0..9 {
-                    if tar[k].count_ones() != 2 {
-                        continue;
-                    }
-                    if tar2[k] != tar[k] {
-                        continue;
-                    }
-                    for t in 0..9 {
-                        if (1 << t) & tar[k] == 0 {
-                            continue;
-                        }
-                        // t行の他のセルからkを取り除く.
-                        let mut col = self.col_mut(t);
-                        for l in 0..9 {
-                            if l == i || l == j {
-                                continue;
-                            }
-                            col[t] -= 1u16 << k;
-                        }
-                    }
+            }
+            len != trace.len()
+        } {}
+        Ok(trace)
+    }
+
+    // 数字ごとの, 未確定の候補として持つマスのビット列
+    pub(super) fn candidate_masks(&self) -> [u128; 9] {
+        let mut ret = [0; 9];
+        for i in 0..81 {
+            let cell = &self[index(i)];
+            for (k, mask) in ret.iter_mut().enumerate() {
+                if !cell.is_filled() && cell.bit() & (1 << k) != 0 {
+                    *mask |= 1 << i;
                 }
             }
         }
+        ret
+    }
+
+    // posと同じ行/列/ブロックに属する自分以外のマス
+    pub(super) fn peers(&self, pos: PuzzleIndex) -> impl Iterator<Item = PuzzleIndex> {
+        (0..81)
+            .map(|i| PuzzleIndex::new(i / 9, i % 9))
+            .filter(move |i| i.is_peer(&pos))
     }
 
-    fn xy_wing(&mut self) {
+    // 各列/行/ブロックにおいて, あるビットが一つの部分(行や列, ブロック)にのみ含まれていた場合, その部分の全体からそのビットを取り除く.
+    // ブロックから行/列へ取り除くのがPointing, 行/列からブロックへ取り除くのがClaiming.
+    fn box_line_reduction(&self, technique: TechniqueKind) -> Vec<Step> {
+        let mut ret = vec![];
         for i in 0..9 {
-            for j in 0..9 {
-                let idx = PuzzleIndex::new(i, j);
-                if self[idx].bit().count_ones() != 2 {
-                    continue;
+            if technique == TechniqueKind::Pointing {
+                let block = self.block(i);
+                for c in 0..3 {
+                    let c_idx = block[c * 3].col();
+                    let only = block.chunk_bit_sum(c)
+                        & !block.chunk_bit_sum((c + 1) % 3)
+                        & !block.chunk_bit_sum((c + 2) % 3);
+                    ret.extend(self.reduce(technique, &block, only, self.col(c_idx)));
                 }
-                let mut candidates = HashSet::new();
-                let tar = self.row(idx.row());
-                for i in 0..9 {
-                    if tar[i].is_filled()
-                        || tar[i].bit().count_ones() != 2
-                        || (tar[i].bit() & self[idx].bit()).count_ones() != 1
-                    {
-                        continue;
-                    }
-                    candidates.insert(tar[i].pos());
-                }
-                let tar = self.col(idx.col());
-                for i in 0..9 {
-                    if tar[i].is_filled()
-                        || tar[i].bit().count_ones() != 2
-                        || (tar[i].bit() & self[idx].bit()).count_ones() != 1
-                    {
-                        continue;
-                    }
-                    candidates.insert(tar[i].pos());
+                for r in 0..3 {
+                    let r_idx = block[r].row();
+                    let only = block.stride_bit_sum(r)
+                        & !block.stride_bit_sum((r + 1) % 3)
+                        & !block.stride_bit_sum((r + 2) % 3);
+                    ret.extend(self.reduce(technique, &block, only, self.row(r_idx)));
                 }
-                let tar = self.block(idx.block_idx());
-                for i in 0..9 {
-                    if tar[i].is_filled()
-                        || tar[i].bit().count_ones() != 2
-                        || (tar[i].bit() & self[idx].bit()).count_ones() != 1
-                    {
-                        continue;
-                    }
-                    candidates.insert(tar[i].pos());
-                }
-                fn is_same_group(i: &Cell, j: &Cell, k: &Cell) -> bool {
-                    (i.row() == j.row() && i.row() == k.row())
-                        || (i.col() == j.col() && i.col() == k.col())
-                        || (i.block_idx() == j.block_idx() && i.block_idx() == k.block_idx())
-                }
-                // 立っているビットの数が2かつself[idx]と一つだけ立っているビットが共通しているセルの集合
-                let candidates = candidates.into_iter().collect::<Vec<_>>();
-                for i in 0..candidates.len() {
-                    for j in i + 1..candidates.len() {
-                        let i = candidates[i];
-                        let j = candidates[j];
-                        // 三つのセルの論理和の立っているビットが3つで, self[idx]は二つのセルと異なる共通セルを持つ.
-                        if !is_same_group(&self[idx], &self[i], &self[j])
-                            && (self[i].bit()
-                                | self[j].bit()
-                                | self[idx].bit())
-                            .count_ones()
-                                == 3
-                            && (self[i].bit() & self[idx].bit()) != (self[j].bit() & self[idx].bit())
-                        {
-                            let common = self[i].bit()
-                                & self[j].bit();
-                            // candidates[i]とcandidates[j]の共通の影響範囲からcommonを取り除く
-                            // お互いの行/列の交差点
-                            self[PuzzleIndex::new(i.col(), j.row())] -= common;
-                            self[PuzzleIndex::new(j.col(), i.row())] -= common;
-                            // iのブロックかつjの行/列
-                            let mut block = self.block_mut(i.block_idx());
-                            for k in 0..9 {
-                                if block[k].pos() == i || block[k].pos() == j {
-                                    continue;
-                                }
-                                if block[k].col() == j.col() {
-                                    block[k] -= common;
-                                }
-                                if block[k].row() == j.row() {
-                                    block[k] -= common;
-                                }
-                            }
-                            // jのブロックかつiの行/列
-                            let mut block = self.block_mut(j.block_idx());
-                            for k in 0..9 {
-                                if block[k].pos() == i || block[k].pos() == j {
-                                    continue;
-                                }
-                                if block[k].col() == i.col() {
-                                    block[k] -= common;
-                                }
-                                if block[k].row() == i.row() {
-                                    block[k] -= common;
-                                }
-                            }
-                        }
+            } else {
+                for line in [self.row(i), self.col(i)] {
+                    for b in 0..3 {
+                        let b_idx = line[b * 3].block_idx();
+                        let only = line.chunk_bit_sum(b)
+                            & !line.chunk_bit_sum((b + 1) % 3)
+                            & !line.chunk_bit_sum((b + 2) % 3);
+                        ret.extend(self.reduce(technique, &line, only, self.block(b_idx)));
                     }
                 }
             }
         }
+        ret
     }
 
-    fn naked_triple(&mut self) {
-        for i in 0..9 {
-            self.block_mut(i).naked_triple();
-            self.col_mut(i).naked_triple();
-            self.row_mut(i).naked_triple();
+    // baseの中でdigitsがtargetとの交差部分にのみ含まれるとき, targetの交差部分以外のマスからdigitsを取り除く.
+    fn reduce(
+        &self,
+        technique: TechniqueKind,
+        base: &PuzzleSlice,
+        digits: u16,
+        target: PuzzleSlice,
+    ) -> Vec<Step> {
+        let mut ret = vec![];
+        for k in 0..9 {
+            if digits & (1 << k) == 0 {
+                continue;
+            }
+            let cells = base
+                .iter()
+                .filter(|c| !c.is_filled() && c.bit() & (1 << k) != 0)
+                .map(|c| c.pos())
+                .collect();
+            let mut step = Step::new(technique, cells, 1 << k);
+            for c in target.iter() {
+                if !base.contains(c.pos()) {
+                    step.eliminate(c, 1 << k);
+                }
+            }
+            ret.push(step);
         }
+        ret
     }
+}
 
-    fn naked_pair(&mut self) {
-        // 各行/列/ブロックにおいて, ある二つのセルのビット和のcount_onesが2に等しいならば, 他のセルからそのビット和を取り除く.
-        for i in 0..9 {
-            self.block_mut(i).naked_pair();
-            self.col_mut(i).naked_pair();
-            self.row_mut(i).naked_pair();
+// マスを (col * 9 + row) 番目のビットとするビット列で扱うための関数
+pub(super) fn index(i: usize) -> PuzzleIndex {
+    PuzzleIndex::new(i / 9, i % 9)
+}
+
+pub(super) fn bits(mask: u128) -> impl Iterator<Item = usize> {
+    (0..81).filter(move |i| mask & (1 << i) != 0)
+}
+
+// マスごとの, 同じ行/列/ブロックに属する自分以外のマス
+pub(super) fn peer_masks() -> Vec<u128> {
+    (0..81)
+        .map(|i| {
+            (0..81)
+                .filter(|&j| index(i).is_peer(&index(j)))
+                .fold(0, |acc, j| acc | 1 << j)
+        })
+        .collect()
+}
+
+// itemsからsize個選ぶ組み合わせをすべて返す
+pub(super) fn combinations<T: Copy>(items: &[T], size: usize) -> Vec<Vec<T>> {
+    if size == 0 {
+        return vec![vec![]];
+    }
+    let mut ret = vec![];
+    for i in 0..items.len() {
+        for mut rest in combinations(&items[i + 1..], size - 1) {
+            rest.insert(0, items[i]);
+            ret.push(rest);
         }
     }
+    ret
+}
 
-    fn hidden_single(&mut self) {
-        // 各行/列/ブロックにおいて, あるビットが他のセルに含まれていないならば, そのセルにそのビットを入れる.
-        for i in 0..9 {
-            self.block_mut(i).hidden_single();
-            self.col_mut(i).hidden_single();
-            self.row_mut(i).hidden_single();
+// テスト用. 画面の左上から行ごとに並べた81文字を読む. 数字以外は空きマス
+#[cfg(test)]
+pub(super) fn parse(s: &str) -> Puzzle {
+    let mut puzzle = Puzzle::new();
+    for (i, c) in s.chars().filter(|c| !c.is_whitespace()).enumerate() {
+        if let Some(v) = c.to_digit(10).filter(|&v| v != 0) {
+            puzzle.fill(index(i), v as u8).unwrap();
         }
     }
+    puzzle
+}
 
-    fn hidden_pair(&mut self) {
-        for i in 0..9 {
-            self.block_mut(i).hidden_pair();
-            self.col_mut(i).hidden_pair();
-            self.row_mut(i).hidden_pair();
+// テスト用. 81個のマスの候補を空白で区切り, 画面の左上から行ごとに並べたものを読む.
+// 候補が一つのマスは確定したマスとする
+#[cfg(test)]
+pub(super) fn parse_candidates(s: &str) -> Puzzle {
+    let cells = s
+        .split_whitespace()
+        .map(|t| t.bytes().fold(0u16, |acc, b| acc | 1 << (b - b'1')))
+        .collect::<Vec<_>>();
+    assert_eq!(cells.len(), 81);
+    let mut puzzle = Puzzle::new();
+    for (i, &bit) in cells.iter().enumerate() {
+        if bit.count_ones() == 1 {
+            puzzle
+                .fill(index(i), bit.trailing_zeros() as u8 + 1)
+                .unwrap();
         }
     }
+    for (i, &bit) in cells.iter().enumerate() {
+        puzzle[index(i)] &= bit;
+    }
+    puzzle
+}
 
-    fn box_line_reduction(&mut self) {
-        // 各列/行/ブロックにおいて, あるビットが一つの部分(行や列, ブロック)にのみ含まれていた場合, その部分の全体からそのビットを取り除く
-        for i in 0..9 {
-            let block = self.block_mut(i);
-            let c0_idx = block[0].col();
-            let c1_idx = block[3].col();
-            let c2_idx = block[6].col();
-            let r0_idx = block[0].row();
-            let r1_idx = block[1].row();
-            let r2_idx = block[2].row();
-            let c0 = block.chunk_bit_sum(0);
-            let c1 = block.chunk_bit_sum(1);
-            let c2 = block.chunk_bit_sum(2);
-            let r0 = block.stride_bit_sum(0);
-            let r1 = block.stride_bit_sum(1);
-            let r2 = block.stride_bit_sum(2);
-            let only_c0 = c0 & (c0 ^ c1) & (c0 ^ c2);
-            let only_c1 = c1 & (c1 ^ c0) & (c1 ^ c2);
-            let only_c2 = c2 & (c2 ^ c0) & (c2 ^ c1);
-            let only_r0 = r0 & (r0 ^ r1) & (r0 ^ r2);
-            let only_r1 = r1 & (r1 ^ r0) & (r1 ^ r2);
-            let only_r2 = r2 & (r2 ^ r0) & (r2 ^ r1);
-            for j in 0..9 {
-                let tar = &mut self.col_mut(c0_idx)[j];
-                if tar.pos().block_idx() == i {
-                    continue;
-                }
-                *tar -= only_c0;
-                self.determine(PuzzleIndex::new(j, c0_idx));
-            }
-            for j in 0..9 {
-                let tar = &mut self.col_mut(c1_idx)[j];
-                if tar.pos().block_idx() == i {
-                    continue;
-                }
-                *tar -= only_c1;
-                self.determine(PuzzleIndex::new(j, c1_idx));
-            }
-            for j in 0..9 {
-                let tar = &mut self.col_mut(c2_idx)[j];
-                if tar.pos().block_idx() == i {
-                    continue;
-                }
-                *tar -= only_c2;
-                self.determine(PuzzleIndex::new(j, c2_idx));
-            }
-            for j in 0..9 {
-                let tar = &mut self.row_mut(r0_idx)[j];
-                if tar.pos().block_idx() == i {
-                    continue;
-                }
-                *tar -= only_r0;
-                self.determine(PuzzleIndex::new(j, r0_idx));
-            }
-            for j in 0..9 {
-                let tar = &mut self.row_mut(r1_idx)[j];
-                if tar.pos().block_idx() == i {
-                    continue;
-                }
-                *tar -= only_r1;
-                self.determine(PuzzleIndex::new(j, r1_idx));
-            }
-            for j in 0..9 {
-                let tar = &mut self.row_mut(r2_idx)[j];
-                if tar.pos().block_idx() == i {
-                    continue;
-                }
-                *tar -= only_r2;
-                self.determine(PuzzleIndex::new(j, r2_idx));
-            }
-            let row = self.row_mut(i);
-            let b0_idx = row[0].block_idx();
-            let b1_idx = row[3].block_idx();
-            let b2_idx = row[6].block_idx();
-            let b0 = row.chunk_bit_sum(0);
-            let b1 = row.chunk_bit_sum(1);
-            let b2 = row.chunk_bit_sum(2);
-            let only_b0 = b0 & (b0 ^ b1) & (b0 ^ b2);
-            let only_b1 = b1 & (b1 ^ b0) & (b1 ^ b2);
-            let only_b2 = b2 & (b2 ^ b0) & (b2 ^ b1);
-            for j in 0..9 {
-                let tar = &mut self.block_mut(b0_idx)[j];
-                if tar.pos().row() == i {
-                    continue;
-                }
-                *tar -= only_b0;
-                let idx = self.block(b0_idx)[j].pos();
-                self.determine(idx);
-            }
-            for j in 0..9 {
-                let tar = &mut self.block_mut(b1_idx)[j];
-                if tar.pos().row() == i {
-                    continue;
-                }
-                *tar -= only_b1;
-                let idx = self.block(b1_idx)[j].pos();
-                self.determine(idx);
-            }
-            for j in 0..9 {
-                let tar = &mut self.block_mut(b2_idx)[j];
-                if tar.pos().row() == i {
-                    continue;
-                }
-                *tar -= only_b2;
-                let idx = self.block(b2_idx)[j].pos();
-                self.determine(idx);
-            }
-            let col = self.col_mut(i);
-            let b0_idx = col[0].block_idx();
-            let b1_idx = col[3].block_idx();
-            let b2_idx = col[6].block_idx();
-            let b0 = col.chunk_bit_sum(0);
-            let b1 = col.chunk_bit_sum(1);
-            let b2 = col.chunk_bit_sum(2);
-            let only_b0 = b0 & (b0 ^ b1) & (b0 ^ b2);
-            let only_b1 = b1 & (b1 ^ b0) & (b1 ^ b2);
-            let only_b2 = b2 & (b2 ^ b0) & (b2 ^ b1);
-            for j in 0..9 {
-                let tar = &mut self.block_mut(b0_idx)[j];
-                if tar.pos().col() == i {
-                    continue;
-                }
-                *tar -= only_b0;
-                let idx = self.block(b0_idx)[j].pos();
-                self.determine(idx);
-            }
-            for j in 0..9 {
-                let tar = &mut self.block_mut(b1_idx)[j];
-                if tar.pos().col() == i {
-                    continue;
-                }
-                *tar -= only_b1;
-                let idx = self.block(b1_idx)[j].pos();
-                self.determine(idx);
-            }
-            for j in 0..9 {
-                let tar = &mut self.block_mut(b2_idx)[j];
-                if tar.pos().col() == i {
-                    continue;
-                }
-                *tar -= only_b2;
-                let idx = self.block(b2_idx)[j].pos();
-                self.determine(idx);
-            }
+// テスト用. techniqueでexpectedを結論とする手順が見つかり, どの手順もバックトラックで求めた解と食い違わないことを確かめる.
+// expectedは手順の表示の "=>" より後と同じ形 ("r1c2=1, r3c4<>56")
+#[cfg(test)]
+pub(super) fn assert_finds(puzzle: &Puzzle, technique: TechniqueKind, expected: &str) {
+    let super::Uniqueness::Unique(solution) = puzzle.uniqueness() else {
+        panic!("puzzle must have one solution");
+    };
+    let steps = puzzle.find_steps(technique);
+    for step in &steps {
+        for &(pos, value) in step.placements() {
+            assert_eq!(solution[pos].bit(), 1 << (value - 1), "{}", step);
+        }
+        for &(pos, bit) in step.eliminations() {
+            assert_eq!(solution[pos].bit() & bit, 0, "{}", step);
         }
     }
+    // 表示の "=>" より後から, 分岐の説明 "(...)" を除いたもの
+    let conclusions = steps
+        .iter()
+        .map(|step| {
+            let text = step.to_string();
+            let (_, conclusion) = text.split_once(" => ").unwrap();
+            conclusion.split(" (").next().unwrap().to_string()
+        })
+        .collect::<Vec<_>>();
+    assert!(
+        conclusions.iter().any(|c| c == expected),
+        "no {} with {}: {:?}",
+        technique,
+        expected,
+        conclusions
+    );
+}
 
-    fn hidden_triple(&mut self) {
-        for i in 0..9 {
-            self.block_mut(i).hidden_triple();
-            self.col_mut(i).hidden_triple();
-            self.row_mut(i).hidden_triple();
-        }
+impl Default for Puzzle {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
@@ -546,3 +510,155 @@ impl IndexMut<PuzzleIndex> for Puzzle {
         &mut self.inner[index.col()][index.row()]
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn next_hint_uses_simplest_technique() {
+        // Naked Pairなどでも候補を消せるが, Hidden Singleを先に返す
+        let puzzle = parse(
+            "..2.7.4...79.2.5....1..5.9..281...6.63..........34.......9...5..1....3......1....",
+        );
+        assert!(!puzzle.find_steps(TechniqueKind::NakedPair).is_empty());
+        let hint = puzzle.next_hint().unwrap();
+        assert_eq!(hint.technique(), TechniqueKind::HiddenSingle);
+    }
+
+    #[test]
+    fn next_hint_gives_up_promptly() {
+        // Easter Monster. 基本的な解法では何も見つからない
+        let puzzle = parse(
+            "1.......2.9.4...5...6...7...5.9.3.......7.......85..4.7.....6...3...9.8...2.....1",
+        );
+        let start = std::time::Instant::now();
+        assert!(puzzle.next_hint().is_none());
+        assert!(Puzzle::new().next_hint().is_none());
+        assert!(start.elapsed() < std::time::Duration::from_secs(1));
+    }
+
+    #[test]
+    fn pointing() {
+        let puzzle = parse_candidates(
+            "
+             1   5  8 3    6     4   7    2   9
+             7   3  4 29   289   1   68   68  5
+             2   6  9 57   578   58  4    3   1
+             3   48 6 1    245   7   9    458 248
+             49  7  2 8    3459  59  1    456 346
+             489 1  5 6    2349  29  238  48  7
+             458 48 7 245  1     3   2568 9   2468
+             6   9  1 245  2458  258 2358 7   2348
+             458 2  3 4579 45789 6   58   1   48
+            ",
+        );
+        assert_finds(&puzzle, TechniqueKind::Pointing, "r2c5<>8");
+    }
+
+    #[test]
+    fn claiming() {
+        let puzzle = parse_candidates(
+            "
+             1   5  8 3    6    4   7    2   9
+             7   3  4 29   29   1   68   68  5
+             2   6  9 57   578  58  4    3   1
+             3   48 6 1    245  7   9    458 28
+             49  7  2 8    3459 59  1    456 36
+             489 1  5 6    2349 29  238  48  7
+             458 48 7 245  1    3   2568 9   2468
+             6   9  1 245  258  258 2358 7   2348
+             458 2  3 4579 5789 6   58   1   48
+            ",
+        );
+        assert_finds(&puzzle, TechniqueKind::Claiming, "r5c5<>9, r6c5<>9");
+    }
+
+    #[test]
+    fn fingerprint_follows_candidates() {
+        let mut puzzle = Puzzle::new();
+        let empty = puzzle.fingerprint();
+        assert_eq!(Puzzle::new().fingerprint(), empty);
+        puzzle[index(0)] -= 1u16;
+        let eliminated = puzzle.fingerprint();
+        assert_ne!(eliminated, empty);
+        // 候補を一つ残したマスと, その数字を入れたマスは区別する
+        puzzle[index(1)] &= 2u16;
+        let single = puzzle.fingerprint();
+        puzzle[index(1)].insert(2);
+        assert_ne!(puzzle.fingerprint(), single);
+    }
+
+    #[test]
+    fn solve_to_fixpoint_stops_when_nothing_changes() {
+        let mut puzzle = parse(
+            "..2.7.4...79.2.5....1..5.9..281...6.63..........34.......9...5..1....3......1....",
+        );
+        assert_eq!(puzzle.solve(), Ok(true));
+        puzzle.solve_to_fixpoint().unwrap();
+        let fingerprint = puzzle.fingerprint();
+        assert_eq!(puzzle.solve(), Ok(false));
+        assert_eq!(puzzle.fingerprint(), fingerprint);
+    }
+
+    #[test]
+    fn fill_rejects_invalid_digits() {
+        let mut puzzle = parse("5........");
+        for v in [0, 10] {
+            assert_eq!(
+                puzzle.fill(index(1), v),
+                Err(SudokuError::DigitOutOfRange {
+                    pos: index(1),
+                    digit: v
+                })
+            );
+        }
+        // r1c1の5と同じ行
+        assert_eq!(
+            puzzle.fill(index(8), 5),
+            Err(SudokuError::DuplicateDigit {
+                slice: SliceType::Col(0),
+                digit: 5,
+                cells: vec![index(0), index(8)],
+            })
+        );
+        assert!(!puzzle[index(1)].is_filled());
+        assert!(!puzzle[index(8)].is_filled());
+        assert_eq!(puzzle.fill(index(8), 4), Ok(()));
+    }
+
+    #[test]
+    fn apply_stops_on_contradiction() {
+        // r1c1に1が入っているので, r1c2に1を入れる手順は適用できない
+        let mut puzzle = parse("1........");
+        let mut step = Step::new(TechniqueKind::NakedSingle, vec![], 0);
+        step.place(&puzzle[index(1)], 1);
+        assert!(matches!(
+            puzzle.apply(&step),
+            Err(SudokuError::DuplicateDigit { digit: 1, .. })
+        ));
+        assert!(!puzzle[index(1)].is_filled());
+
+        // 候補をすべて消す手順も矛盾
+        let mut puzzle = Puzzle::new();
+        let mut step = Step::new(TechniqueKind::NakedSingle, vec![], 0);
+        step.eliminate(&puzzle[index(8)], 0b111111111);
+        assert_eq!(
+            puzzle.apply(&step),
+            Err(SudokuError::NoCandidates(index(8)))
+        );
+    }
+
+    #[test]
+    fn solve_reports_first_contradiction() {
+        // r1c1の候補は1だけなのに, r1c2に1が入っている
+        let mut puzzle = Puzzle::new();
+        puzzle[index(0)] &= 1u16;
+        puzzle[index(1)].insert(1);
+        // 手順を適用したときのエラーではなく, validateで見つかる最初の矛盾を返す
+        assert!(matches!(
+            puzzle.solve(),
+            Err(SudokuError::CandidateConflict { digit: 1, .. })
+        ));
+    }
+}
@@ -1,4 +1,4 @@
-use std::hash::Hash;
+use std::{fmt::Display, hash::Hash};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct PuzzleIndex {
@@ -22,6 +22,21 @@ impl PuzzleIndex {
     pub fn block_idx(&self) -> usize {
         self.col / 3 * 3 + self.row / 3
     }
+
+    // 自分以外で同じ行/列/ブロックに属しているか
+    pub fn is_peer(&self, other: &Self) -> bool {
+        self != other
+            && (self.row == other.row
+                || self.col == other.col
+                || self.block_idx() == other.block_idx())
+    }
+}
+
+impl Display for PuzzleIndex {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // colが画面の行, rowが画面の列にあたる
+        write!(f, "r{}c{}", self.col + 1, self.row + 1)
+    }
 }
 
 impl Hash for PuzzleIndex {
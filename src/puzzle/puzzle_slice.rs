@@ -1,15 +1,29 @@
-use std::ops::{Index, IndexMut};
+use std::{
+    fmt::Display,
+    ops::{Index, IndexMut},
+};
 
 use crate::cell::Cell;
 
-use super::{Puzzle, PuzzleIndex};
-#[derive(Clone, Copy, Debug)]
-pub(super) enum SliceType {
+use super::{puzzle::combinations, step::TechniqueKind, Puzzle, PuzzleIndex, Step, SudokuError};
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SliceType {
     Row(usize),
     Col(usize),
     Block(usize),
 }
 
+impl Display for SliceType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            // PuzzleIndexと同じく, Colが画面の行, Rowが画面の列
+            Self::Row(n) => write!(f, "c{}", n + 1),
+            Self::Col(n) => write!(f, "r{}", n + 1),
+            Self::Block(n) => write!(f, "b{}", n + 1),
+        }
+    }
+}
+
 pub struct PuzzleSlice<'a> {
     puzzle: &'a Puzzle,
     pub(super) type_: SliceType,
@@ -24,16 +38,157 @@ impl<'a> PuzzleSlice<'a> {
         PuzzleIter::new(self, 0)
     }
 
+    pub fn contains(&self, pos: PuzzleIndex) -> bool {
+        match self.type_ {
+            SliceType::Row(n) => pos.row() == n,
+            SliceType::Col(n) => pos.col() == n,
+            SliceType::Block(n) => pos.block_idx() == n,
+        }
+    }
+
+    pub fn chunk_bit_sum(&self, chunk: usize) -> u16 {
+        self[chunk * 3].bit() | self[chunk * 3 + 1].bit() | self[chunk * 3 + 2].bit()
+    }
+
+    pub fn stride_bit_sum(&self, stride: usize) -> u16 {
+        self[stride].bit() | self[stride + 3].bit() | self[stride + 6].bit()
+    }
+
+    pub fn chunk_bit_product(&self, chunk: usize) -> u16 {
+        self[chunk * 3].bit() & self[chunk * 3 + 1].bit() & self[chunk * 3 + 2].bit()
+    }
+
+    pub fn stride_bit_product(&self, stride: usize) -> u16 {
+        self[stride].bit() & self[stride + 3].bit() & self[stride + 6].bit()
+    }
+
     pub fn to_number_appearance(&self) -> [u16; 9] {
         // 各数字がどのマスに現れているかを表すビット列を返す
         let mut ret = [0; 9];
         for i in 0..9 {
-            for j in 0..9 {
-                ret[j] |= self[i].bit() & (1 << j);
+            for (j, r) in ret.iter_mut().enumerate() {
+                *r |= (self[i].bit() & (1 << j)) >> j << i;
+            }
+        }
+        ret
+    }
+
+    // 数字kが未確定の候補として現れる位置のビット列
+    pub fn candidate_positions(&self, k: usize) -> u16 {
+        (0..9)
+            .filter(|&i| !self[i].is_filled() && self[i].bit() & (1 << k) != 0)
+            .fold(0, |acc, i| acc | 1 << i)
+    }
+
+    // size個のマスの候補の和がsize個の数字ならば, 他のマスからその数字を取り除く.
+    // 各マスがすべての数字を持っている必要はない. size == 1ならばその数字を入れる.
+    pub(super) fn naked_subset(&self, size: usize) -> Vec<Step> {
+        let technique = match size {
+            1 => TechniqueKind::NakedSingle,
+            2 => TechniqueKind::NakedPair,
+            3 => TechniqueKind::NakedTriple,
+            _ => TechniqueKind::NakedQuad,
+        };
+        let cells = (0..9)
+            .filter(|&i| !self[i].is_filled() && self[i].bit().count_ones() as usize <= size)
+            .collect::<Vec<_>>();
+        let mut ret = vec![];
+        for cells in combinations(&cells, size) {
+            let digits = cells.iter().fold(0, |acc, &i| acc | self[i].bit());
+            if digits.count_ones() as usize != size {
+                continue;
+            }
+            let cells = cells.iter().fold(0, |acc, &i| acc | 1 << i);
+            if size == 1 {
+                ret.push(self.single(technique, cells, digits));
+            } else {
+                ret.push(self.exclude(technique, cells, digits));
             }
         }
         ret
     }
+
+    // size個の数字の候補が現れるマスの和がsize個ならば, そのマスから他の数字を取り除く.
+    // 各数字がすべてのマスに現れている必要はない. size == 1ならばその数字を入れる.
+    pub(super) fn hidden_subset(&self, size: usize) -> Vec<Step> {
+        let technique = match size {
+            1 => TechniqueKind::HiddenSingle,
+            2 => TechniqueKind::HiddenPair,
+            3 => TechniqueKind::HiddenTriple,
+            _ => TechniqueKind::HiddenQuad,
+        };
+        let digits = (0..9)
+            .map(|k| (k, self.candidate_positions(k)))
+            .filter(|(_, a)| *a != 0 && a.count_ones() as usize <= size)
+            .collect::<Vec<_>>();
+        let mut ret = vec![];
+        for digits in combinations(&digits, size) {
+            let cells = digits.iter().fold(0, |acc, (_, a)| acc | a);
+            if cells.count_ones() as usize != size {
+                continue;
+            }
+            let digits = digits.iter().fold(0, |acc, (k, _)| acc | 1 << k);
+            if size == 1 {
+                ret.push(self.single(technique, cells, digits));
+            } else {
+                ret.push(self.restrict(technique, cells, digits));
+            }
+        }
+        ret
+    }
+
+    // n個のマスの候補の和がn+1個の数字となるマスの組 (Almost Locked Set) と, その数字
+    pub(super) fn almost_locked_sets(&self) -> Vec<(Vec<PuzzleIndex>, u16)> {
+        let cells = (0..9).filter(|&i| !self[i].is_filled()).collect::<Vec<_>>();
+        let mut ret = vec![];
+        for size in 1..cells.len() {
+            for cells in combinations(&cells, size) {
+                let digits = cells.iter().fold(0, |acc, &i| acc | self[i].bit());
+                if digits.count_ones() as usize == size + 1 {
+                    let cells = cells.iter().fold(0, |acc, &i| acc | 1 << i);
+                    ret.push((self.positions(cells), digits));
+                }
+            }
+        }
+        ret
+    }
+
+    // cellsの一つのマスにdigitsの一つの数字を入れる手順
+    fn single(&self, technique: TechniqueKind, cells: u16, digits: u16) -> Step {
+        let cell = &self[cells.trailing_zeros() as usize];
+        let mut step = Step::new(technique, vec![cell.pos()], digits);
+        step.place(cell, digits.trailing_zeros() as u8 + 1);
+        step
+    }
+
+    // cells (スライス内の位置のビット列) 以外のマスからdigitsを取り除く手順
+    fn exclude(&self, technique: TechniqueKind, cells: u16, digits: u16) -> Step {
+        let mut step = Step::new(technique, self.positions(cells), digits);
+        for k in 0..9 {
+            if cells & (1 << k) == 0 {
+                step.eliminate(&self[k], digits);
+            }
+        }
+        step
+    }
+
+    // cellsのマスの候補をdigitsのみに絞る手順
+    fn restrict(&self, technique: TechniqueKind, cells: u16, digits: u16) -> Step {
+        let mut step = Step::new(technique, self.positions(cells), digits);
+        for k in 0..9 {
+            if cells & (1 << k) != 0 {
+                step.eliminate(&self[k], !digits);
+            }
+        }
+        step
+    }
+
+    fn positions(&self, cells: u16) -> Vec<PuzzleIndex> {
+        (0..9)
+            .filter(|k| cells & (1 << k) != 0)
+            .map(|k| self[k].pos())
+            .collect()
+    }
 }
 
 impl<'a> Index<usize> for PuzzleSlice<'a> {
@@ -85,147 +240,33 @@ impl<'a> PuzzleSliceMut<'a> {
         PuzzleIter::new(self, 0)
     }
 
+    pub fn as_slice(&self) -> PuzzleSlice<'_> {
+        PuzzleSlice::new(self.puzzle, self.type_)
+    }
+
     pub fn chunk_bit_sum(&self, chunk: usize) -> u16 {
-        self[chunk * 3].bit() | self[chunk * 3 + 1].bit() | self[chunk * 3 + 2].bit()
+        self.as_slice().chunk_bit_sum(chunk)
     }
 
     pub fn stride_bit_sum(&self, stride: usize) -> u16 {
-        self[stride].bit() | self[stride + 3].bit() | self[stride + 6].bit()
+        self.as_slice().stride_bit_sum(stride)
     }
 
     pub fn chunk_bit_product(&self, chunk: usize) -> u16 {
-        self[chunk].bit() & self[chunk + 1].bit() & self[chunk + 2].bit()
+        self.as_slice().chunk_bit_product(chunk)
     }
 
     pub fn stride_bit_product(&self, stride: usize) -> u16 {
-        self[stride].bit() & self[stride + 3].bit() & self[stride + 6].bit()
+        self.as_slice().stride_bit_product(stride)
     }
 
-    pub fn determine(&mut self, index: usize) {
+    pub fn determine(&mut self, index: usize) -> Result<(), SudokuError> {
         let pos = self[index].pos();
-        self.puzzle.determine(pos);
-    }
-    pub fn to_number_appearance(&self) -> [u16; 9] {
-        // 各数字がどのマスに現れているかを表すビット列を返す
-        let bits = (0..9)
-            .map(|i| self[i].bit())
-            .enumerate()
-            .collect::<Vec<_>>();
-        let mut ret = [0; 9];
-        for (i, bit) in bits.iter() {
-            for j in 0..9 {
-                ret[j] |= (*bit & (1 << j)) >> j << i;
-            }
-        }
-        ret
+        self.puzzle.determine(pos)
     }
 
-    pub(super) fn hidden_pair(&mut self) {
-        let appearance = self.to_number_appearance();
-        for i in 0..9 {
-            for j in i + 1..9 {
-                if appearance[i] != appearance[j] || appearance[i].count_ones() != 2 {
-                    continue;
-                }
-                for k in 0..9 {
-                    if (1 << k) & appearance[i] == 0 {
-                        continue;
-                    }
-                    self[k] &= (1 << i) | (1u16 << j);
-                }
-            }
-        }
-    }
-
-    pub(super) fn hidden_single(&mut self) {
-        let appearance = self.to_number_appearance();
-        for i in 0..9 {
-            if appearance[i].count_ones() != 1 {
-                continue;
-            }
-            self.puzzle.fill(
-                self[appearance[i].trailing_zeros() as usize].pos(),
-                i as u8 + 1,
-            );
-        }
-    }
-
-    pub(super) fn naked_pair(&mut self) {
-        for i in 0..9 {
-            if self[i].is_filled() {
-                continue;
-            }
-            for j in i + 1..9 {
-                if self[j].is_filled() {
-                    continue;
-                }
-                let bit = self[i].bit() | self[j].bit();
-                if bit.count_ones() != 2 {
-                    continue;
-                }
-                for k in 0..9 {
-                    if i == k || j == k {
-                        continue;
-                    }
-                    self[k] -= bit;
-                    self.determine(k);
-                }
-            }
-        }
-    }
-
-    pub(super) fn naked_triple(&mut self) {
-        for i in 0..9 {
-            if self[i].is_filled() {
-                continue;
-            }
-            for j in i + 1..9 {
-                if self[j].is_filled() {
-                    continue;
-                }
-                for k in j + 1..9 {
-                    if self[k].is_filled() {
-                        continue;
-                    }
-                    let bit = self[i].bit() | self[j].bit() | self[k].bit();
-                    if bit.count_ones() != 3 {
-                        continue;
-                    }
-                    for l in 0..9 {
-                        if i == l || j == l || k == l {
-                            continue;
-                        }
-                        self[l] -= bit;
-                        self.determine(l);
-                    }
-                }
-            }
-        }
-    }
-
-    pub(super) fn hidden_triple(&mut self) {
-        let appearance = self.to_number_appearance();
-        for i in 0..9 {
-            if appearance[i].count_ones() != 3 {
-                continue;
-            }
-            for j in i + 1..9 {
-                if appearance[i] != appearance[j]{
-                    continue;
-                }
-                for k in j + 1..9 {
-                    if appearance[i] != appearance[k]{
-                        continue;
-                    }
-                    for l in 0..9 {
-                        if (1 << l) & appearance[i] == 0 {
-                            continue;
-                        }
-                        self[l] &= (1 << i) | (1 << j) | (1u16 << k);
-                    }
-                }
-            }
-        }
+    pub fn to_number_appearance(&self) -> [u16; 9] {
+        self.as_slice().to_number_appearance()
     }
 }
 
@@ -308,3 +349,159 @@ impl<'a> Iterator for PuzzleIter<'a> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::super::puzzle::{assert_finds, index, parse_candidates};
+    use super::*;
+
+    #[test]
+    fn naked_single() {
+        // 候補を消しただけでは確定しないので, r2c4に候補が一つだけ残る
+        let mut puzzle = parse_candidates(
+            "
+             1   5  8 3    6     4   7    2   9
+             7   3  4 29   289   1   68   68  5
+             2   6  9 57   578   58  4    3   1
+             3   48 6 1    245   7   9    458 248
+             49  7  2 8    3459  59  1    456 346
+             489 1  5 6    2349  29  238  48  7
+             458 48 7 245  1     3   2568 9   2468
+             6   9  1 245  2458  258 2358 7   2348
+             458 2  3 4579 45789 6   58   1   48
+            ",
+        );
+        puzzle[index(12)] -= 1u16 << 1;
+        assert_finds(&puzzle, TechniqueKind::NakedSingle, "r2c4=9");
+    }
+
+    #[test]
+    fn hidden_single() {
+        let puzzle = parse_candidates(
+            "
+             358     568   2     68     7   13689  4     138   1368
+             348     7     9     468    2   13468  5     138   1368
+             348     468   1     468    368 5      2678  9     23678
+             4579    2     8     1      59  79     79    6     34579
+             6       3     457   2578   589 2789   12789 12478 1245789
+             1579    59    57    3      4   26789  12789 1278  125789
+             23478   468   3467  9      368 234678 12678 5     124678
+             245789  1     4567  245678 568 24678  3     2478  246789
+             2345789 45689 34567 245678 1   234678 26789 2478  246789
+            ",
+        );
+        assert_finds(&puzzle, TechniqueKind::HiddenSingle, "r1c6=9");
+    }
+
+    #[test]
+    fn naked_pair() {
+        let puzzle = parse_candidates(
+            "
+             4    8 29   256  3   25   69 7 1
+             239  1 239  8    246 7    69 5 24
+             6    7 5    124  9   12   8  3 24
+             123  4 7    235  125 6    23 8 9
+             1238 9 238  123  7   1238 4  6 5
+             238  5 6    9    28  4    23 1 7
+             5    3 1489 14   148 189  7  2 6
+             7    2 14   1346 46  13   5  9 8
+             89   6 89   7    258 2589 1  4 3
+            ",
+        );
+        assert_finds(&puzzle, TechniqueKind::NakedPair, "r7c3<>89");
+    }
+
+    #[test]
+    fn hidden_pair() {
+        let puzzle = parse_candidates(
+            "
+             4     8    29    1256  3    125   69 7 12
+             239   1    2379  8     246  27    69 5 24
+             6     27   5     1247  9    127   8  3 124
+             123   2457 2347  1235  125  6     23 8 9
+             12389 29   2389  1239  7    12389 4  6 5
+             2389  2569 23689 2359  258  4     23 1 7
+             5     3    1489  149   148  189   7  2 6
+             7     246  1246  12346 1246 123   5  9 8
+             289   269  2689  25679 2568 25789 1  4 3
+            ",
+        );
+        assert_finds(&puzzle, TechniqueKind::HiddenPair, "r4c2<>25, r4c3<>23");
+    }
+
+    #[test]
+    fn naked_triple() {
+        let puzzle = parse_candidates(
+            "
+             1   5  8 3    6    4   7    2   9
+             7   3  4 29   29   1   68   68  5
+             2   6  9 57   578  58  4    3   1
+             3   48 6 1    245  7   9    458 28
+             49  7  2 8    345  59  1    456 36
+             489 1  5 6    234  29  238  48  7
+             458 48 7 245  1    3   2568 9   2468
+             6   9  1 245  258  258 2358 7   2348
+             458 2  3 4579 5789 6   58   1   48
+            ",
+        );
+        assert_finds(&puzzle, TechniqueKind::NakedTriple, "r9c4<>45, r9c5<>58");
+    }
+
+    #[test]
+    fn hidden_triple() {
+        let puzzle = parse_candidates(
+            "
+             4  8  16 25   25   3   17  67  9
+             79 5  13 4    79   6   138 38  2
+             67 39 2  79   1    8   34  346 5
+             2  7  89 689  689  4   35  35  1
+             5  1  48 3    278  27  247 9   6
+             3  6  49 2579 2579 1   247 247 8
+             89 2  5  78   3    79  6   1   4
+             1  49 7  268  2468 259 258 258 3
+             68 34 36 1    248  25  9   258 7
+            ",
+        );
+        assert_finds(&puzzle, TechniqueKind::HiddenTriple, "r3c8<>3, r6c8<>2");
+    }
+
+    #[test]
+    fn naked_quad() {
+        let puzzle = parse_candidates(
+            "
+             468 7   346  368 2   14    13469 5   1349
+             58  9   346  78  13  57    46    2   134
+             1   356 2    36  9   45    34    8   7
+             49  2   49   37  8   17    5     137 6
+             3   16  8    29  5   12679 147   17  124
+             567 156 67   4   136 236   8     9   123
+             267 4   5    29  367 2369  1379  137 8
+             679 36  3679 1   67  8     2     4   5
+             27  8   1    5   4   239   379   6   39
+            ",
+        );
+        assert_finds(&puzzle, TechniqueKind::NakedQuad, "r5c6<>17");
+    }
+
+    #[test]
+    fn hidden_quad() {
+        let puzzle = parse_candidates(
+            "
+             236   1  4    37     38   367 236  5  9
+             2369  27 679  3579   359  4   1    8  27
+             369   5  6789 1379   1389 2   36   46 47
+             7     6  15   135    2    135 4    9  8
+             15    9  3    8      145  15  7    2  6
+             8     4  2    6      7    9   5    1  3
+             4     27 1579 12579  6    8   29   3  12
+             1269  8  1679 123479 1349 137 269  46 5
+             12569 3  1569 12459  1459 15  2689 7  124
+            ",
+        );
+        assert_finds(
+            &puzzle,
+            TechniqueKind::HiddenQuad,
+            "r2c1<>2, r2c3<>7, r2c4<>7",
+        );
+    }
+}
@@ -0,0 +1,193 @@
+use super::{puzzle::combinations, step::TechniqueKind, Puzzle, PuzzleIndex, SliceType, Step};
+
+impl Puzzle {
+    // 数字kの候補がちょうど二つのマスにしかない行/列/ブロックの, その二つのマスの組 (強リンク)
+    pub(super) fn strong_links(&self, k: usize) -> Vec<(PuzzleIndex, PuzzleIndex)> {
+        let mut ret = vec![];
+        for i in 0..9 {
+            for type_ in [SliceType::Row(i), SliceType::Col(i), SliceType::Block(i)] {
+                let positions = self.slice(type_).candidate_positions(k);
+                if positions.count_ones() != 2 {
+                    continue;
+                }
+                let slice = self.slice(type_);
+                let a = slice[positions.trailing_zeros() as usize].pos();
+                let b = slice[15 - positions.leading_zeros() as usize].pos();
+                if !ret.contains(&(a, b)) {
+                    ret.push((a, b));
+                }
+            }
+        }
+        ret
+    }
+
+    // 二つの強リンクA=BとC=Dにおいて, BとCが互いに見えるならばAかDのどちらかにkが入るので, AとDの両方が見えるマスからkを取り除く.
+    // 平行な行/列の組ならSkyscraper, 行と列の組でBとCが同じブロックにあれば2-String Kite, それ以外はTurbot Fish.
+    pub(super) fn turbot_fish(&self, technique: TechniqueKind) -> Vec<Step> {
+        let mut ret = vec![];
+        for k in 0..9 {
+            let links = self.strong_links(k);
+            for pair in combinations(&links, 2) {
+                let ((p, q), (r, s)) = (pair[0], pair[1]);
+                for ((a, b), (c, d)) in [
+                    ((p, q), (r, s)),
+                    ((p, q), (s, r)),
+                    ((q, p), (r, s)),
+                    ((q, p), (s, r)),
+                ] {
+                    let cells = [a, b, c, d];
+                    if combinations(&cells, 2).iter().any(|x| x[0] == x[1]) || !b.is_peer(&c) {
+                        continue;
+                    }
+                    let same_row = |x: PuzzleIndex, y: PuzzleIndex| x.row() == y.row();
+                    let same_col = |x: PuzzleIndex, y: PuzzleIndex| x.col() == y.col();
+                    let kind = if (same_row(a, b) && same_row(c, d) && same_col(b, c))
+                        || (same_col(a, b) && same_col(c, d) && same_row(b, c))
+                    {
+                        TechniqueKind::Skyscraper
+                    } else if ((same_row(a, b) && same_col(c, d))
+                        || (same_col(a, b) && same_row(c, d)))
+                        && b.block_idx() == c.block_idx()
+                        && a.block_idx() != b.block_idx()
+                        && c.block_idx() != d.block_idx()
+                    {
+                        TechniqueKind::TwoStringKite
+                    } else {
+                        TechniqueKind::TurbotFish
+                    };
+                    if kind != technique {
+                        continue;
+                    }
+                    let mut step = Step::new(kind, cells.to_vec(), 1 << k);
+                    for x in self.peers(a) {
+                        if x.is_peer(&d) && !cells.contains(&x) {
+                            step.eliminate(&self[x], 1 << k);
+                        }
+                    }
+                    ret.push(step);
+                }
+            }
+        }
+        ret
+    }
+
+    // ブロック内の数字kの候補が一つの行と一つの列の十字に収まっているとき, その行(列)の外の強リンクを通して,
+    // 十字の列(行)とリンクの他端の行(列)の交点からkを取り除く.
+    pub(super) fn empty_rectangle(&self) -> Vec<Step> {
+        let mut ret = vec![];
+        for b in 0..9 {
+            for k in 0..9 {
+                let block = self.block(b);
+                let positions = block.candidate_positions(k);
+                let cells = (0..9)
+                    .filter(|i| positions & (1 << i) != 0)
+                    .map(|i| block[i].pos())
+                    .collect::<Vec<_>>();
+                if cells.len() < 2 {
+                    continue;
+                }
+                let rows = (0..3).map(|i| block[i].row()).collect::<Vec<_>>();
+                let cols = (0..3).map(|i| block[i * 3].col()).collect::<Vec<_>>();
+                for &r in &rows {
+                    for &c in &cols {
+                        if cells.iter().any(|p| p.row() != r && p.col() != c)
+                            || cells.iter().all(|p| p.row() == r)
+                            || cells.iter().all(|p| p.col() == c)
+                        {
+                            continue;
+                        }
+                        for (x, y) in self.strong_links(k) {
+                            for (x, y) in [(x, y), (y, x)] {
+                                // xが十字の行にありyがブロックの外の行にある列のリンク
+                                let target = if x.col() == y.col()
+                                    && x.row() == r
+                                    && !rows.contains(&y.row())
+                                    && !cols.contains(&x.col())
+                                {
+                                    PuzzleIndex::new(c, y.row())
+                                // xが十字の列にありyがブロックの外の列にある行のリンク
+                                } else if x.row() == y.row()
+                                    && x.col() == c
+                                    && !cols.contains(&y.col())
+                                    && !rows.contains(&x.row())
+                                {
+                                    PuzzleIndex::new(y.col(), r)
+                                } else {
+                                    continue;
+                                };
+                                let mut step = Step::new(
+                                    TechniqueKind::EmptyRectangle,
+                                    cells.iter().copied().chain([x, y]).collect(),
+                                    1 << k,
+                                );
+                                step.eliminate(&self[target], 1 << k);
+                                ret.push(step);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        ret
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::puzzle::{assert_finds, parse_candidates};
+    use super::*;
+
+    #[test]
+    fn skyscraper() {
+        let puzzle = parse_candidates(
+            "
+             1789  4    379  189  13   2    6  19  5
+             1569  3569 3569 1469 1356 345  7  2   8
+             15689 2    569  1689 7    58   3  149 49
+             569   8    569  1247 1235 345  14 37  467
+             3     7    4    18   9    6    18 5   2
+             2     56   1    478  35   3458 9  378 467
+             67    36   2367 5    4    9    28 78  1
+             4579  59   2579 3    8    1    24 6   479
+             49    1    8    26   26   7    5  49  3
+            ",
+        );
+        assert_finds(&puzzle, TechniqueKind::Skyscraper, "r3c1<>9, r3c3<>9");
+    }
+
+    #[test]
+    fn two_string_kite() {
+        let puzzle = parse_candidates(
+            "
+             1   5  8 3   6   4   7    2   9
+             7   3  4 29  29  1   68   68  5
+             2   6  9 57  578 58  4    3   1
+             3   48 6 1   245 7   9    458 28
+             49  7  2 8   345 59  1    456 36
+             489 1  5 6   234 29  238  48  7
+             458 48 7 245 1   3   2568 9   2468
+             6   9  1 245 258 258 235  7   234
+             458 2  3 79  79  6   58   1   48
+            ",
+        );
+        assert_finds(&puzzle, TechniqueKind::TwoStringKite, "r8c9<>2");
+    }
+
+    #[test]
+    fn empty_rectangle() {
+        let puzzle = parse_candidates(
+            "
+             139 279 2359 12   37  8   59  6   4
+             139 249 2359 12   346 346 7   8   59
+             68  47  68   47   5   9   123 123 23
+             7   5   1    68   36  2   389 4   39
+             2   89  39   478  347 347 6   5   1
+             36  68  4    9    1   5   238 237 237
+             5   269 269  3    8   1   4   279 267
+             468 3   268  4567 9   467 125 12  256
+             469 1   7    456  2   46  35  39  8
+            ",
+        );
+        assert_finds(&puzzle, TechniqueKind::EmptyRectangle, "r2c1<>3");
+    }
+}
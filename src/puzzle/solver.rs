@@ -0,0 +1,358 @@
+use std::time::{Duration, Instant};
+
+use super::{chain::MAX_CHAIN_LENGTH, Puzzle, Step, SudokuError, TechniqueKind};
+
+// 盤面から手順を探す解法. TechniqueKindの他に, 独自の解法を作ってSolverに加えることもできる
+pub trait Technique {
+    fn name(&self) -> &str;
+
+    fn find_steps(&self, puzzle: &Puzzle) -> Vec<Step>;
+
+    // 鎖を使う解法では, 節点の数がmax_chain_length以下の鎖だけを探す. Solverはこちらを使う
+    fn find_steps_within(&self, puzzle: &Puzzle, max_chain_length: usize) -> Vec<Step> {
+        let _ = max_chain_length;
+        self.find_steps(puzzle)
+    }
+
+    // 解が一つに定まることを前提とする解法か. SolverはSolver::assume_uniqueを指定したときだけ使う
+    fn requires_uniqueness(&self) -> bool {
+        false
+    }
+
+    // 見つけた手順をすべて適用する. 盤面が変わればtrue. 矛盾が出たらそこで止める
+    fn apply(&self, puzzle: &mut Puzzle) -> Result<bool, SudokuError> {
+        let mut changed = false;
+        for step in self.find_steps(puzzle) {
+            changed |= puzzle.apply(&step)?;
+        }
+        Ok(changed)
+    }
+}
+
+impl Technique for TechniqueKind {
+    fn name(&self) -> &str {
+        TechniqueKind::name(self)
+    }
+
+    fn find_steps(&self, puzzle: &Puzzle) -> Vec<Step> {
+        puzzle.find_steps(*self)
+    }
+
+    fn find_steps_within(&self, puzzle: &Puzzle, max_chain_length: usize) -> Vec<Step> {
+        puzzle.find_steps_within(*self, max_chain_length)
+    }
+
+    fn requires_uniqueness(&self) -> bool {
+        TechniqueKind::requires_uniqueness(self)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Strategy {
+    // 盤面が変わるたびに最初の解法からやり直す
+    Restart,
+    // 各解法を順に一度ずつ使う
+    SinglePass,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopReason {
+    Solved,
+    // どの解法でも盤面が変わらなくなった
+    Stalled,
+    // SinglePassで最後の解法まで使った
+    PassCompleted,
+    StepLimit,
+    TimeLimit,
+}
+
+#[derive(Debug, Clone)]
+pub struct SolveReport {
+    steps: Vec<Step>,
+    stop_reason: StopReason,
+}
+
+impl SolveReport {
+    // 実際に盤面を変えた手順
+    pub fn steps(&self) -> &[Step] {
+        &self.steps
+    }
+
+    pub fn stop_reason(&self) -> StopReason {
+        self.stop_reason
+    }
+}
+
+pub struct Solver {
+    techniques: Vec<Box<dyn Technique>>,
+    strategy: Strategy,
+    max_steps: Option<usize>,
+    time_limit: Option<Duration>,
+    assume_unique: bool,
+    max_chain_length: usize,
+}
+
+impl Solver {
+    // 基本的な解法 (TechniqueKind::BASIC) を難しさの順に使い, 変化があるたびにやり直す.
+    // すべての解法を使うには.techniques(&TechniqueKind::ALL)とする
+    pub fn new() -> Self {
+        Self {
+            techniques: vec![],
+            strategy: Strategy::Restart,
+            max_steps: None,
+            time_limit: None,
+            assume_unique: false,
+            max_chain_length: MAX_CHAIN_LENGTH,
+        }
+        .techniques(&TechniqueKind::BASIC)
+    }
+
+    // 使う解法をkindsだけにし, この順に使う
+    pub fn techniques(mut self, kinds: &[TechniqueKind]) -> Self {
+        self.techniques = kinds
+            .iter()
+            .map(|&k| Box::new(k) as Box<dyn Technique>)
+            .collect();
+        self
+    }
+
+    // 解法を最後に加える
+    pub fn technique(mut self, technique: impl Technique + 'static) -> Self {
+        self.techniques.push(Box::new(technique));
+        self
+    }
+
+    pub fn disable(mut self, kind: TechniqueKind) -> Self {
+        self.techniques.retain(|t| t.name() != kind.name());
+        self
+    }
+
+    pub fn strategy(mut self, strategy: Strategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    // 盤面を変えた手順がこの数に達したら止める
+    pub fn max_steps(mut self, max_steps: usize) -> Self {
+        self.max_steps = Some(max_steps);
+        self
+    }
+
+    // 解法を一つ使い終わるごとに経過時間を確かめ, 超えていたら止める.
+    // 一つの解法の探索の途中では止まらないので, Forcing ChainやExocetなど時間のかかる解法では超えることがある
+    pub fn time_limit(mut self, time_limit: Duration) -> Self {
+        self.time_limit = Some(time_limit);
+        self
+    }
+
+    // trueにするとUnique RectangleやBUG+1も使う. 解が一つに定まる問題でだけ指定する
+    pub fn assume_unique(mut self, assume_unique: bool) -> Self {
+        self.assume_unique = assume_unique;
+        self
+    }
+
+    // X-ChainやAICなどで探す鎖の節点の数の上限. 既定は12
+    pub fn max_chain_length(mut self, max_chain_length: usize) -> Self {
+        self.max_chain_length = max_chain_length;
+        self
+    }
+
+    // 使える解法
+    fn enabled(&self) -> impl Iterator<Item = &dyn Technique> {
+        self.techniques
+            .iter()
+            .map(|t| t.as_ref())
+            .filter(|t| self.assume_unique || !t.requires_uniqueness())
+    }
+
+    // 盤面は変えずに, 使える解法のうち最初に見つかる手順を一つ返す
+    pub fn next_hint(&self, puzzle: &Puzzle) -> Option<Step> {
+        self.enabled().find_map(|t| {
+            t.find_steps_within(puzzle, self.max_chain_length)
+                .into_iter()
+                .next()
+        })
+    }
+
+    // 手順を適用して矛盾が出たらそこで止め, エラーを返す
+    pub fn solve(&self, puzzle: &mut Puzzle) -> Result<SolveReport, SudokuError> {
+        let start = Instant::now();
+        let mut steps = vec![];
+        let stop_reason = 'solve: loop {
+            let mut progressed = false;
+            for technique in self.enabled() {
+                if puzzle.is_solved() {
+                    break 'solve StopReason::Solved;
+                }
+                if self.time_limit.is_some_and(|t| start.elapsed() >= t) {
+                    break 'solve StopReason::TimeLimit;
+                }
+                for step in technique.find_steps_within(puzzle, self.max_chain_length) {
+                    if self.max_steps.is_some_and(|m| steps.len() >= m) {
+                        break 'solve StopReason::StepLimit;
+                    }
+                    // 先に適用した手順で既に消えた候補は記録しない
+                    let step = step.pruned(puzzle);
+                    if puzzle.apply(&step)? {
+                        steps.push(step);
+                        progressed = true;
+                    }
+                }
+                if progressed && self.strategy == Strategy::Restart {
+                    break;
+                }
+            }
+            if puzzle.is_solved() {
+                break StopReason::Solved;
+            }
+            match self.strategy {
+                Strategy::SinglePass => break StopReason::PassCompleted,
+                Strategy::Restart if !progressed => break StopReason::Stalled,
+                Strategy::Restart => {}
+            }
+        };
+        Ok(SolveReport { steps, stop_reason })
+    }
+}
+
+impl Default for Solver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::puzzle::{index, parse, parse_candidates};
+    use super::*;
+
+    #[test]
+    fn solve_stops_on_contradiction() {
+        // r1c1の候補は1だけなのに, r1c2に1が入っている
+        let mut puzzle = Puzzle::new();
+        puzzle[index(0)] &= 1u16;
+        puzzle[index(1)].insert(1);
+        assert!(matches!(
+            Solver::new().solve(&mut puzzle),
+            Err(SudokuError::DuplicateDigit { digit: 1, .. })
+        ));
+    }
+
+    // Hidden Singleだけで解ける問題
+    fn hidden_singles() -> Puzzle {
+        parse("..2.7.4...79.2.5....1..5.9..281...6.63..........34.......9...5..1....3......1....")
+    }
+
+    #[test]
+    fn restart_goes_back_to_the_simplest_technique() {
+        let mut puzzle = hidden_singles();
+        let report = Solver::new().solve(&mut puzzle).unwrap();
+        assert_eq!(report.stop_reason(), StopReason::Solved);
+        assert_eq!(report.steps().len(), 9);
+        assert!(report
+            .steps()
+            .iter()
+            .all(|s| s.technique() == TechniqueKind::HiddenSingle));
+    }
+
+    #[test]
+    fn single_pass_uses_each_technique_once() {
+        let mut puzzle = hidden_singles();
+        let report = Solver::new()
+            .strategy(Strategy::SinglePass)
+            .solve(&mut puzzle)
+            .unwrap();
+        assert_eq!(report.stop_reason(), StopReason::PassCompleted);
+        assert_eq!(report.steps().len(), 37);
+        assert!(!puzzle.is_solved());
+    }
+
+    #[test]
+    fn stalls_when_no_technique_applies() {
+        // Easter Monster. 基本的な解法では何も見つからない
+        let mut puzzle = parse(
+            "1.......2.9.4...5...6...7...5.9.3.......7.......85..4.7.....6...3...9.8...2.....1",
+        );
+        let report = Solver::new().solve(&mut puzzle).unwrap();
+        assert_eq!(report.stop_reason(), StopReason::Stalled);
+        assert!(report.steps().is_empty());
+    }
+
+    #[test]
+    fn max_steps_stops_the_solver() {
+        let mut puzzle = hidden_singles();
+        let report = Solver::new().max_steps(3).solve(&mut puzzle).unwrap();
+        assert_eq!(report.stop_reason(), StopReason::StepLimit);
+        assert_eq!(report.steps().len(), 3);
+    }
+
+    #[test]
+    fn time_limit_stops_the_solver() {
+        let mut puzzle = hidden_singles();
+        let report = Solver::new()
+            .time_limit(Duration::ZERO)
+            .solve(&mut puzzle)
+            .unwrap();
+        assert_eq!(report.stop_reason(), StopReason::TimeLimit);
+        assert!(report.steps().is_empty());
+    }
+
+    #[test]
+    fn disabled_technique_is_not_used() {
+        let mut puzzle = hidden_singles();
+        let report = Solver::new()
+            .disable(TechniqueKind::HiddenSingle)
+            .solve(&mut puzzle)
+            .unwrap();
+        // 他の解法で解ける
+        assert_eq!(report.stop_reason(), StopReason::Solved);
+        assert!(report
+            .steps()
+            .iter()
+            .all(|s| s.technique() != TechniqueKind::HiddenSingle));
+    }
+
+    #[test]
+    fn custom_technique_is_used() {
+        // Hidden Singleを探すだけの独自の解法
+        struct Custom;
+        impl Technique for Custom {
+            fn name(&self) -> &str {
+                "Custom"
+            }
+
+            fn find_steps(&self, puzzle: &Puzzle) -> Vec<Step> {
+                TechniqueKind::HiddenSingle.find_steps(puzzle)
+            }
+        }
+        let mut puzzle = hidden_singles();
+        let report = Solver::new()
+            .techniques(&[])
+            .technique(Custom)
+            .solve(&mut puzzle)
+            .unwrap();
+        assert_eq!(report.stop_reason(), StopReason::Solved);
+        assert_eq!(report.steps().len(), 9);
+    }
+
+    #[test]
+    fn max_chain_length_limits_chains() {
+        // chain::tests::xchainの盤面. 見つかる鎖は6個の節点からなる
+        let puzzle = parse_candidates(
+            "
+             46     3     5     27    46   1    2789  789   2789
+             1267   1268  9     237   5    2678 13    4     27
+             1247   1248  1278  2379  2348 2789 13    6     5
+             8      1259  123   12379 1236 4    2579  579   2679
+             2349   249   23    5     236  2679 24789 1     26789
+             12459  7     6     8     12   29   2459  59    3
+             59     589   4     6     7    3    589   2     1
+             123567 12568 12378 4     9    258  578   3578  78
+             23579  259   2378  12    128  258  6     35789 4
+            ",
+        );
+        let solver = Solver::new().techniques(&[TechniqueKind::XChain]);
+        assert!(solver.next_hint(&puzzle).is_some());
+        assert!(solver.max_chain_length(5).next_hint(&puzzle).is_none());
+    }
+}
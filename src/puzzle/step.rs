@@ -0,0 +1,412 @@
+use std::fmt::Display;
+
+use crate::cell::Cell;
+
+use super::{Puzzle, PuzzleIndex, SliceType};
+
+// 難しさの順に並んでいる
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum TechniqueKind {
+    NakedSingle,
+    HiddenSingle,
+    Pointing,
+    Claiming,
+    NakedPair,
+    NakedTriple,
+    HiddenPair,
+    HiddenTriple,
+    XWing,
+    Swordfish,
+    Skyscraper,
+    TwoStringKite,
+    EmptyRectangle,
+    TurbotFish,
+    XYWing,
+    XYZWing,
+    UniqueRectangle1,
+    UniqueRectangle2,
+    UniqueRectangle3,
+    UniqueRectangle4,
+    UniqueRectangle5,
+    UniqueRectangle6,
+    HiddenRectangle,
+    BugPlusOne,
+    NakedQuad,
+    Jellyfish,
+    HiddenQuad,
+    ColorTrap,
+    ColorWrap,
+    MultiColoring,
+    FinnedXWing,
+    SashimiXWing,
+    FinnedSwordfish,
+    WXYZWing,
+    SashimiSwordfish,
+    FinnedJellyfish,
+    SashimiJellyfish,
+    SueDeCoq,
+    XChain,
+    XYChain,
+    Medusa3D,
+    XCycle,
+    AIC,
+    GroupedAIC,
+    AlsXz,
+    AlsXyWing,
+    DeathBlossom,
+    Nishio,
+    CellForcingChain,
+    UnitForcingChain,
+    SkLoop,
+    Msls,
+    JuniorExocet,
+    SeniorExocet,
+}
+
+impl TechniqueKind {
+    // 手間のかからない基本的な解法. Solver::newとPuzzle::solveはこれだけを使う
+    pub const BASIC: [TechniqueKind; 10] = [
+        Self::NakedSingle,
+        Self::HiddenSingle,
+        Self::Pointing,
+        Self::Claiming,
+        Self::NakedPair,
+        Self::NakedTriple,
+        Self::HiddenPair,
+        Self::HiddenTriple,
+        Self::XWing,
+        Self::XYWing,
+    ];
+
+    pub const ALL: [TechniqueKind; 54] = [
+        Self::NakedSingle,
+        Self::HiddenSingle,
+        Self::Pointing,
+        Self::Claiming,
+        Self::NakedPair,
+        Self::NakedTriple,
+        Self::HiddenPair,
+        Self::HiddenTriple,
+        Self::XWing,
+        Self::Swordfish,
+        Self::Skyscraper,
+        Self::TwoStringKite,
+        Self::EmptyRectangle,
+        Self::TurbotFish,
+        Self::XYWing,
+        Self::XYZWing,
+        Self::UniqueRectangle1,
+        Self::UniqueRectangle2,
+        Self::UniqueRectangle3,
+        Self::UniqueRectangle4,
+        Self::UniqueRectangle5,
+        Self::UniqueRectangle6,
+        Self::HiddenRectangle,
+        Self::BugPlusOne,
+        Self::NakedQuad,
+        Self::Jellyfish,
+        Self::HiddenQuad,
+        Self::ColorTrap,
+        Self::ColorWrap,
+        Self::MultiColoring,
+        Self::FinnedXWing,
+        Self::SashimiXWing,
+        Self::FinnedSwordfish,
+        Self::WXYZWing,
+        Self::SashimiSwordfish,
+        Self::FinnedJellyfish,
+        Self::SashimiJellyfish,
+        Self::SueDeCoq,
+        Self::XChain,
+        Self::XYChain,
+        Self::Medusa3D,
+        Self::XCycle,
+        Self::AIC,
+        Self::GroupedAIC,
+        Self::AlsXz,
+        Self::AlsXyWing,
+        Self::DeathBlossom,
+        Self::Nishio,
+        Self::CellForcingChain,
+        Self::UnitForcingChain,
+        Self::SkLoop,
+        Self::Msls,
+        Self::JuniorExocet,
+        Self::SeniorExocet,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::NakedSingle => "Naked Single",
+            Self::HiddenSingle => "Hidden Single",
+            Self::Pointing => "Pointing",
+            Self::Claiming => "Claiming",
+            Self::NakedPair => "Naked Pair",
+            Self::NakedTriple => "Naked Triple",
+            Self::HiddenPair => "Hidden Pair",
+            Self::HiddenTriple => "Hidden Triple",
+            Self::XWing => "X-Wing",
+            Self::Swordfish => "Swordfish",
+            Self::Skyscraper => "Skyscraper",
+            Self::TwoStringKite => "2-String Kite",
+            Self::EmptyRectangle => "Empty Rectangle",
+            Self::TurbotFish => "Turbot Fish",
+            Self::XYWing => "XY-Wing",
+            Self::XYZWing => "XYZ-Wing",
+            Self::UniqueRectangle1 => "Unique Rectangle Type 1",
+            Self::UniqueRectangle2 => "Unique Rectangle Type 2",
+            Self::UniqueRectangle3 => "Unique Rectangle Type 3",
+            Self::UniqueRectangle4 => "Unique Rectangle Type 4",
+            Self::UniqueRectangle5 => "Unique Rectangle Type 5",
+            Self::UniqueRectangle6 => "Unique Rectangle Type 6",
+            Self::HiddenRectangle => "Hidden Rectangle",
+            Self::BugPlusOne => "BUG+1",
+            Self::NakedQuad => "Naked Quad",
+            Self::Jellyfish => "Jellyfish",
+            Self::HiddenQuad => "Hidden Quad",
+            Self::ColorTrap => "Simple Colors Trap",
+            Self::ColorWrap => "Simple Colors Wrap",
+            Self::MultiColoring => "Multi Colors",
+            Self::FinnedXWing => "Finned X-Wing",
+            Self::SashimiXWing => "Sashimi X-Wing",
+            Self::FinnedSwordfish => "Finned Swordfish",
+            Self::WXYZWing => "WXYZ-Wing",
+            Self::SashimiSwordfish => "Sashimi Swordfish",
+            Self::FinnedJellyfish => "Finned Jellyfish",
+            Self::SashimiJellyfish => "Sashimi Jellyfish",
+            Self::SueDeCoq => "Sue de Coq",
+            Self::XChain => "X-Chain",
+            Self::XYChain => "XY-Chain",
+            Self::Medusa3D => "3D Medusa",
+            Self::XCycle => "X-Cycle",
+            Self::AIC => "AIC",
+            Self::GroupedAIC => "Grouped AIC",
+            Self::AlsXz => "ALS-XZ",
+            Self::AlsXyWing => "ALS-XY-Wing",
+            Self::DeathBlossom => "Death Blossom",
+            Self::Nishio => "Nishio",
+            Self::CellForcingChain => "Cell Forcing Chain",
+            Self::UnitForcingChain => "Unit Forcing Chain",
+            Self::SkLoop => "SK-Loop",
+            Self::Msls => "Multi-Sector Locked Set",
+            Self::JuniorExocet => "Junior Exocet",
+            Self::SeniorExocet => "Senior Exocet",
+        }
+    }
+}
+
+impl TechniqueKind {
+    // 解が一つに定まることを前提とする解法か
+    pub fn requires_uniqueness(&self) -> bool {
+        matches!(
+            self,
+            Self::UniqueRectangle1
+                | Self::UniqueRectangle2
+                | Self::UniqueRectangle3
+                | Self::UniqueRectangle4
+                | Self::UniqueRectangle5
+                | Self::UniqueRectangle6
+                | Self::HiddenRectangle
+                | Self::BugPlusOne
+        )
+    }
+}
+
+impl Display for TechniqueKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+// 解法を一回適用した記録. 候補はCellと同じくビット列で持つ.
+#[derive(Debug, Clone)]
+pub struct Step {
+    technique: TechniqueKind,
+    cells: Vec<PuzzleIndex>,
+    digits: u16,
+    // 基底集合と被覆集合 (魚など行/列/ブロックの組で表されるパターンのみ)
+    base: Vec<SliceType>,
+    cover: Vec<SliceType>,
+    // 鎖のEureka記法 (鎖で表されるパターンのみ)
+    chain: Option<String>,
+    // 仮定から結論までの枝 (Forcing Chainなど仮定を置く解法のみ)
+    branches: Vec<String>,
+    eliminations: Vec<(PuzzleIndex, u16)>,
+    placements: Vec<(PuzzleIndex, u8)>,
+}
+
+impl Step {
+    pub(super) fn new(technique: TechniqueKind, cells: Vec<PuzzleIndex>, digits: u16) -> Self {
+        Self {
+            technique,
+            cells,
+            digits,
+            base: vec![],
+            cover: vec![],
+            chain: None,
+            branches: vec![],
+            eliminations: vec![],
+            placements: vec![],
+        }
+    }
+
+    pub fn technique(&self) -> TechniqueKind {
+        self.technique
+    }
+
+    // パターンを構成するマス
+    pub fn cells(&self) -> &[PuzzleIndex] {
+        &self.cells
+    }
+
+    pub fn digits(&self) -> u16 {
+        self.digits
+    }
+
+    pub fn base(&self) -> &[SliceType] {
+        &self.base
+    }
+
+    pub fn cover(&self) -> &[SliceType] {
+        &self.cover
+    }
+
+    pub fn chain(&self) -> Option<&str> {
+        self.chain.as_deref()
+    }
+
+    pub fn branches(&self) -> &[String] {
+        &self.branches
+    }
+
+    pub fn eliminations(&self) -> &[(PuzzleIndex, u16)] {
+        &self.eliminations
+    }
+
+    pub fn placements(&self) -> &[(PuzzleIndex, u8)] {
+        &self.placements
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.eliminations.is_empty() && self.placements.is_empty()
+    }
+
+    pub(super) fn with_sets(mut self, base: Vec<SliceType>, cover: Vec<SliceType>) -> Self {
+        self.base = base;
+        self.cover = cover;
+        self
+    }
+
+    pub(super) fn with_chain(mut self, chain: String) -> Self {
+        self.chain = Some(chain);
+        self
+    }
+
+    pub(super) fn with_branches(mut self, branches: Vec<String>) -> Self {
+        self.branches = branches;
+        self
+    }
+
+    // cellに実際に残っている候補だけを取り除く対象にする.
+    pub(super) fn eliminate(&mut self, cell: &Cell, bit: u16) {
+        if cell.is_filled() || cell.bit() & bit == 0 {
+            return;
+        }
+        let bit = cell.bit() & bit;
+        match self.eliminations.iter_mut().find(|(p, _)| *p == cell.pos()) {
+            Some((_, b)) => *b |= bit,
+            None => self.eliminations.push((cell.pos(), bit)),
+        }
+    }
+
+    pub(super) fn retain_eliminations(&mut self, f: impl Fn(&PuzzleIndex) -> bool) {
+        self.eliminations.retain(|(p, _)| f(p));
+    }
+
+    pub(super) fn place(&mut self, cell: &Cell, value: u8) {
+        if cell.is_filled() || self.placements.iter().any(|(p, _)| *p == cell.pos()) {
+            return;
+        }
+        self.placements.push((cell.pos(), value));
+    }
+
+    // puzzleに対してまだ効果のある部分だけを残した手順を返す.
+    pub(super) fn pruned(&self, puzzle: &Puzzle) -> Self {
+        let mut ret = Self {
+            eliminations: vec![],
+            placements: vec![],
+            ..self.clone()
+        };
+        for &(pos, bit) in &self.eliminations {
+            ret.eliminate(&puzzle[pos], bit);
+        }
+        for &(pos, value) in &self.placements {
+            ret.place(&puzzle[pos], value);
+        }
+        ret
+    }
+}
+
+pub(super) fn digits_to_string(bit: u16) -> String {
+    (0..9)
+        .filter(|i| bit & (1 << i) != 0)
+        .map(|i| char::from(b'1' + i as u8))
+        .collect()
+}
+
+impl Display for Step {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:", self.technique)?;
+        if let Some(chain) = &self.chain {
+            write!(f, " {}", chain)?;
+        } else {
+            for pos in &self.cells {
+                write!(f, " {}", pos)?;
+            }
+            write!(f, " {{{}}}", digits_to_string(self.digits))?;
+        }
+        if !self.base.is_empty() {
+            let base = self.base.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+            let cover = self.cover.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+            write!(f, " {}/{}", base.join(""), cover.join(""))?;
+        }
+        write!(f, " =>")?;
+        let conclusions = self
+            .placements
+            .iter()
+            .map(|(pos, value)| format!("{}={}", pos, value))
+            .chain(
+                self.eliminations
+                    .iter()
+                    .map(|(pos, bit)| format!("{}<>{}", pos, digits_to_string(*bit))),
+            )
+            .collect::<Vec<_>>();
+        write!(f, " {}", conclusions.join(", "))?;
+        if !self.branches.is_empty() {
+            write!(f, " ({})", self.branches.join("; "))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::puzzle::parse;
+    use super::*;
+
+    #[test]
+    fn hidden_single_is_written_in_screen_coordinates() {
+        // 1行目で1が入るのはr1c2だけ
+        let puzzle = parse(
+            "......... ...1..... ......1.. ......... 1........ \
+             ......... ......... ..1...... .........",
+        );
+        let steps = puzzle.find_steps(TechniqueKind::HiddenSingle);
+        assert!(!steps.is_empty());
+        for step in steps {
+            assert_eq!(step.to_string(), "Hidden Single: r1c2 {1} => r1c2=1");
+        }
+        assert_eq!(SliceType::Col(0).to_string(), "r1");
+        assert_eq!(SliceType::Row(1).to_string(), "c2");
+    }
+}
@@ -0,0 +1,114 @@
+use super::{puzzle::combinations, step::TechniqueKind, Puzzle, PuzzleIndex, SliceType, Step};
+
+impl Puzzle {
+    // ブロックと行/列の交差部分のマスCの候補Vが |C| + 2 個以上あるとき, 行/列の残りのマスLとブロックの残りのマスBを
+    // Lの候補とBの候補が重ならず, マスの数と候補の数が等しくなるように選ぶ. このとき各数字はちょうど一回ずつ入るので,
+    // 行/列の他のマスからはLの候補とVのうちBにないものを, ブロックの他のマスからはBの候補とVのうちLにないものを取り除く.
+    pub(super) fn sue_de_coq(&self) -> Vec<Step> {
+        let mut ret = vec![];
+        for b in 0..9 {
+            let block = self.block(b);
+            // 交差部分 (ブロックのchunk/stride) と, それを含む列/行
+            let intersections = (0..3)
+                .map(|c| {
+                    (
+                        SliceType::Col(block[c * 3].col()),
+                        [c * 3, c * 3 + 1, c * 3 + 2],
+                    )
+                })
+                .chain((0..3).map(|r| (SliceType::Row(block[r].row()), [r, r + 3, r + 6])));
+            for (line, positions) in intersections {
+                let line = self.slice(line);
+                let cross = positions
+                    .iter()
+                    .map(|&i| block[i].pos())
+                    .filter(|p| !self[*p].is_filled())
+                    .collect::<Vec<_>>();
+                let unfilled = |cells: Vec<PuzzleIndex>| {
+                    cells
+                        .into_iter()
+                        .filter(|p| !self[*p].is_filled() && !cross.contains(p))
+                        .collect::<Vec<_>>()
+                };
+                let line_rest = unfilled(line.iter().map(|c| c.pos()).collect());
+                let block_rest = unfilled(block.iter().map(|c| c.pos()).collect());
+                for size in 2..=cross.len() {
+                    for c in combinations(&cross, size) {
+                        let v = self.union(&c);
+                        if (v.count_ones() as usize) < size + 2 {
+                            continue;
+                        }
+                        for l in (1..=line_rest.len()).flat_map(|n| combinations(&line_rest, n)) {
+                            let vl = self.union(&l);
+                            if vl & v == 0 {
+                                continue;
+                            }
+                            for bs in
+                                (1..=block_rest.len()).flat_map(|n| combinations(&block_rest, n))
+                            {
+                                let vb = self.union(&bs);
+                                if vb & v == 0
+                                    || vl & vb != 0
+                                    || (v | vl | vb).count_ones() as usize
+                                        != size + l.len() + bs.len()
+                                {
+                                    continue;
+                                }
+                                let cells = c.iter().chain(&l).chain(&bs).copied().collect();
+                                let mut step =
+                                    Step::new(TechniqueKind::SueDeCoq, cells, v | vl | vb);
+                                for p in &line_rest {
+                                    if !l.contains(p) {
+                                        step.eliminate(&self[*p], (v | vl) & !vb);
+                                    }
+                                }
+                                for p in &block_rest {
+                                    if !bs.contains(p) {
+                                        step.eliminate(&self[*p], (v | vb) & !vl);
+                                    }
+                                }
+                                // 交差部分の選ばなかったマスは行とブロックの両方に属する
+                                for p in &cross {
+                                    if !c.contains(p) {
+                                        step.eliminate(&self[*p], v | vl | vb);
+                                    }
+                                }
+                                ret.push(step);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        ret
+    }
+
+    // cellsの候補の和
+    pub(super) fn union(&self, cells: &[PuzzleIndex]) -> u16 {
+        cells.iter().fold(0, |acc, p| acc | self[*p].bit())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::puzzle::{assert_finds, parse_candidates};
+    use super::*;
+
+    #[test]
+    fn sue_de_coq() {
+        let puzzle = parse_candidates(
+            "
+             1678  2    678 5    3   17  4   89 679
+             5     147  467 1278 278 9   3   28 267
+             78    3    9   278  4   6   5   1  27
+             9     6    3   18   58  15  2   7  4
+             47    5    2   47   9   3   8   6  1
+             1478  1478 478 247  6   27  9   3  5
+             23467 47   467 9    127 8   167 5  23
+             23678 789  1   36   257 257 67  4  239
+             2367  79   5   36   127 4   167 29 8
+            ",
+        );
+        assert_finds(&puzzle, TechniqueKind::SueDeCoq, "r2c9<>2, r3c4<>7");
+    }
+}
@@ -0,0 +1,379 @@
+use super::{puzzle::combinations, step::TechniqueKind, Puzzle, PuzzleIndex, SliceType, Step};
+
+// 四隅が二つのブロックにまたがる長方形. 隣り合う順に並べるので, 対角は (0, 2) と (1, 3)
+fn rectangles() -> Vec<[PuzzleIndex; 4]> {
+    let mut ret = vec![];
+    for rows in combinations(&(0..9).collect::<Vec<_>>(), 2) {
+        for cols in combinations(&(0..9).collect::<Vec<_>>(), 2) {
+            let rect = [
+                PuzzleIndex::new(cols[0], rows[0]),
+                PuzzleIndex::new(cols[1], rows[0]),
+                PuzzleIndex::new(cols[1], rows[1]),
+                PuzzleIndex::new(cols[0], rows[1]),
+            ];
+            let mut blocks = rect.map(|p| p.block_idx()).to_vec();
+            blocks.sort();
+            blocks.dedup();
+            if blocks.len() == 2 {
+                ret.push(rect);
+            }
+        }
+    }
+    ret
+}
+
+// 同じ行か列にあるか
+fn same_line(p: PuzzleIndex, q: PuzzleIndex) -> bool {
+    p.row() == q.row() || p.col() == q.col()
+}
+
+impl Puzzle {
+    // 解が一つに定まることを前提に, 二つの数字a, bだけで四隅を埋められる長方形 (解が二通りになる形) ができないように候補を消す.
+    // 四隅のうちa, b以外の候補も持つマスを屋根と呼ぶ.
+    pub(super) fn unique_rectangle(&self, technique: TechniqueKind) -> Vec<Step> {
+        let mut ret = vec![];
+        for rect in rectangles() {
+            let cells = rect.map(|p| &self[p]);
+            if cells.iter().any(|c| c.is_filled()) {
+                continue;
+            }
+            let common = cells.iter().fold(0x1ff, |acc, c| acc & c.bit());
+            let digits = (0..9)
+                .filter(|k| common & (1 << k) != 0)
+                .collect::<Vec<_>>();
+            for pair in combinations(&digits, 2) {
+                let (a, b) = (pair[0], pair[1]);
+                let bit = 1 << a | 1 << b;
+                let roof = (0..4)
+                    .filter(|&i| cells[i].bit() != bit)
+                    .map(|i| rect[i])
+                    .collect::<Vec<_>>();
+                // 屋根の候補のうちa, b以外のもの
+                let extra = roof.iter().fold(0, |acc, p| acc | self[*p].bit() & !bit);
+                let adjacent = roof.len() == 2 && same_line(roof[0], roof[1]);
+                let mut step = Step::new(technique, rect.to_vec(), bit);
+                match technique {
+                    TechniqueKind::UniqueRectangle1 if roof.len() == 1 => {
+                        step.eliminate(&self[roof[0]], bit);
+                    }
+                    TechniqueKind::UniqueRectangle2 | TechniqueKind::UniqueRectangle5
+                        if (2..=3).contains(&roof.len())
+                            && extra.count_ones() == 1
+                            && adjacent == (technique == TechniqueKind::UniqueRectangle2) =>
+                    {
+                        // 屋根のどれかにextraが入る
+                        for p in self.peers(roof[0]) {
+                            if roof.iter().all(|r| r.is_peer(&p)) {
+                                step.eliminate(&self[p], extra);
+                            }
+                        }
+                    }
+                    TechniqueKind::UniqueRectangle3 if adjacent => {
+                        // 屋根の二マスをextraを候補とする一つのマスとみなして, 他のマスと組ませた Naked Subset
+                        for type_ in self.shared_slices(roof[0], roof[1]) {
+                            let others = self
+                                .slice(type_)
+                                .iter()
+                                .filter(|c| !c.is_filled() && !roof.contains(&c.pos()))
+                                .map(|c| c.pos())
+                                .collect::<Vec<_>>();
+                            for size in 1..=3 {
+                                for subset in combinations(&others, size) {
+                                    let union =
+                                        subset.iter().fold(extra, |acc, p| acc | self[*p].bit());
+                                    if union.count_ones() as usize != size + 1 {
+                                        continue;
+                                    }
+                                    let mut step = Step::new(
+                                        technique,
+                                        rect.iter().chain(&subset).copied().collect(),
+                                        bit,
+                                    );
+                                    for p in &others {
+                                        if !subset.contains(p) {
+                                            step.eliminate(&self[*p], union);
+                                        }
+                                    }
+                                    ret.push(step);
+                                }
+                            }
+                        }
+                        continue;
+                    }
+                    TechniqueKind::UniqueRectangle4 if adjacent => {
+                        // 屋根の共通の行/列/ブロックでxが屋根にしか入らなければ, 屋根からyを消せる
+                        for type_ in self.shared_slices(roof[0], roof[1]) {
+                            for (x, y) in [(a, b), (b, a)] {
+                                if self.only_in(type_, x, &roof) {
+                                    for p in &roof {
+                                        step.eliminate(&self[*p], 1 << y);
+                                    }
+                                }
+                            }
+                        }
+                    }
+                    TechniqueKind::UniqueRectangle6 if roof.len() == 2 && !adjacent => {
+                        // xが長方形の二行と二列で四隅にしか入らなければ, 対角の屋根にはxは入らない
+                        for x in [a, b] {
+                            let lines = rect
+                                .iter()
+                                .flat_map(|p| [SliceType::Row(p.row()), SliceType::Col(p.col())]);
+                            if lines.clone().all(|l| self.only_in(l, x, &rect)) {
+                                for p in &roof {
+                                    step.eliminate(&self[*p], 1 << x);
+                                }
+                            }
+                        }
+                    }
+                    TechniqueKind::HiddenRectangle if (2..=3).contains(&roof.len()) => {
+                        // 候補がa, bだけのマスの対角にあるマスの行と列で, xが四隅にしか入らなければ, そのマスにyは入らない
+                        for i in (0..4).filter(|&i| !roof.contains(&rect[i])) {
+                            let d = rect[(i + 2) % 4];
+                            if !roof.contains(&d) {
+                                continue;
+                            }
+                            for (x, y) in [(a, b), (b, a)] {
+                                if self.only_in(SliceType::Row(d.row()), x, &rect)
+                                    && self.only_in(SliceType::Col(d.col()), x, &rect)
+                                {
+                                    step.eliminate(&self[d], 1 << y);
+                                }
+                            }
+                        }
+                    }
+                    _ => continue,
+                }
+                ret.push(step);
+            }
+        }
+        ret
+    }
+
+    // 候補が三つのマスが一つだけで, 他のマスの候補がすべて二つなら, そのマスには行/列/ブロックで三回現れる数字が入る.
+    // そうでなければ各行/列/ブロックで各数字がちょうど二回ずつ現れ, 解が二通りになる.
+    pub(super) fn bug_plus_one(&self) -> Vec<Step> {
+        let unfilled = (0..81)
+            .map(|i| &self[PuzzleIndex::new(i / 9, i % 9)])
+            .filter(|c| !c.is_filled())
+            .collect::<Vec<_>>();
+        if unfilled
+            .iter()
+            .any(|c| !(2..=3).contains(&c.bit().count_ones()))
+            || unfilled
+                .iter()
+                .filter(|c| c.bit().count_ones() == 3)
+                .count()
+                != 1
+        {
+            return vec![];
+        }
+        let cell = unfilled.iter().find(|c| c.bit().count_ones() == 3).unwrap();
+        let pos = cell.pos();
+        let slices = [
+            SliceType::Row(pos.row()),
+            SliceType::Col(pos.col()),
+            SliceType::Block(pos.block_idx()),
+        ];
+        let Some(k) = (0..9).find(|&k| {
+            slices
+                .iter()
+                .all(|s| self.slice(*s).candidate_positions(k).count_ones() == 3)
+        }) else {
+            return vec![];
+        };
+        // kをそのマスから除いた盤面で, すべての数字がどの行/列/ブロックにも0回か2回現れる
+        let is_bug = (0..9).all(|i| {
+            [SliceType::Row(i), SliceType::Col(i), SliceType::Block(i)]
+                .into_iter()
+                .all(|s| {
+                    let slice = self.slice(s);
+                    (0..9).all(|d| {
+                        let count = slice.candidate_positions(d).count_ones()
+                            - (d == k && slice.contains(pos)) as u32;
+                        count == 0 || count == 2
+                    })
+                })
+        });
+        if !is_bug {
+            return vec![];
+        }
+        let mut step = Step::new(TechniqueKind::BugPlusOne, vec![pos], 1 << k);
+        step.place(cell, k as u8 + 1);
+        vec![step]
+    }
+
+    // pとqの両方を含む行/列/ブロック
+    fn shared_slices(&self, p: PuzzleIndex, q: PuzzleIndex) -> Vec<SliceType> {
+        [
+            SliceType::Row(p.row()),
+            SliceType::Col(p.col()),
+            SliceType::Block(p.block_idx()),
+        ]
+        .into_iter()
+        .filter(|s| self.slice(*s).contains(q))
+        .collect()
+    }
+
+    // type_の中で数字kの候補がcellsにしかないか
+    fn only_in(&self, type_: SliceType, k: usize, cells: &[PuzzleIndex]) -> bool {
+        let slice = self.slice(type_);
+        (0..9)
+            .filter(|&i| slice.candidate_positions(k) & (1 << i) != 0)
+            .all(|i| cells.contains(&slice[i].pos()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::puzzle::{assert_finds, parse_candidates};
+    use super::super::Solver;
+    use super::*;
+
+    #[test]
+    fn unique_rectangle_type_1() {
+        let puzzle = parse_candidates(
+            "
+             1678  2    678 5   3   17 4   89 79
+             5     147  47  128 78  9  3   28 6
+             78    3    9   28  4   6  5   1  27
+             9     6    3   18  58  15 2   7  4
+             47    5    2   47  9   3  8   6  1
+             1478  1478 478 47  6   2  9   3  5
+             23467 47   467 9   127 8  167 5  23
+             23678 789  1   36  257 57 67  4  239
+             2367  79   5   36  127 4  167 29 8
+            ",
+        );
+        assert_finds(&puzzle, TechniqueKind::UniqueRectangle1, "r6c1<>47");
+        // Solverはassume_uniqueを指定したときだけ使う
+        let solver = Solver::new().techniques(&[TechniqueKind::UniqueRectangle1]);
+        assert!(solver.next_hint(&puzzle).is_none());
+        assert!(solver.assume_unique(true).next_hint(&puzzle).is_some());
+    }
+
+    #[test]
+    fn unique_rectangle_type_2() {
+        let puzzle = parse_candidates(
+            "
+             45 1259 1468 124589 157 2458 367 478 348
+             3  25   468  2458   57  2458 67  9   1
+             7  19   148  1489   3   6    2   5   48
+             9  6    134  1245   15  245  8   13  7
+             18 178  2    167    9   3    4   16  5
+             45 1357 1347 1467   8   47   9   136 2
+             6  13   9    578    2   578  135 48  348
+             2  4    37   58     6   1    357 78  9
+             18 178  5    3      4   9    17  2   6
+            ",
+        );
+        assert_finds(&puzzle, TechniqueKind::UniqueRectangle2, "r6c2<>7");
+    }
+
+    #[test]
+    fn unique_rectangle_type_3() {
+        let puzzle = parse_candidates(
+            "
+             9    36 378  2   17  167 358 4 35
+             5    46 2    46  3   8   19  7 19
+             378  1  3478 457 45  9   38  6 2
+             2368 7  3489 568 89  56  24  1 36
+             36   5  13   17  2   4   39  8 3679
+             268  49 1489 3   189 167 24  5 67
+             1    8  5    9   6   2   7   3 4
+             4    2  6    78  78  3   15  9 15
+             37   39 379  145 45  15  6   2 8
+            ",
+        );
+        assert_finds(&puzzle, TechniqueKind::UniqueRectangle3, "r8c4<>7");
+    }
+
+    #[test]
+    fn unique_rectangle_type_4() {
+        let puzzle = parse_candidates(
+            "
+             139 279 2359 12   37  8   59  6   4
+             19  249 2359 12   346 346 7   8   59
+             68  47  68   47   5   9   123 123 23
+             7   5   1    68   36  2   389 4   39
+             2   89  39   478  347 347 6   5   1
+             36  68  4    9    1   5   238 237 237
+             5   269 269  3    8   1   4   279 267
+             468 3   268  4567 9   467 125 12  256
+             469 1   7    456  2   46  35  39  8
+            ",
+        );
+        assert_finds(&puzzle, TechniqueKind::UniqueRectangle4, "r8c1<>6, r8c3<>6");
+    }
+
+    #[test]
+    fn unique_rectangle_type_5() {
+        let puzzle = parse_candidates(
+            "
+             8   5   2    149   134 39  137  346   1347
+             19  14  139  14589 6   7   1238 245   14
+             136 34  7    145   15  238 268  23568 9
+             347 9   6    1257  178 258 238  235   158
+             17  2   18   3     157 4   189  89    6
+             5   138 138  128   9   268 4    7     138
+             139 378 139  6     2   39  5    348   47
+             279 378 4    5789  358 359 68   1     378
+             379 6   3589 578   457 1   379  48    2
+            ",
+        );
+        assert_finds(&puzzle, TechniqueKind::UniqueRectangle5, "r9c3<>3");
+    }
+
+    #[test]
+    fn unique_rectangle_type_6() {
+        let puzzle = parse_candidates(
+            "
+             69  36  679 2    367 4   1    8   5
+             5   24  27  79   1   8   469  46  3
+             1   346 8   69   36  5   7    2   49
+             3   7   4   1    5   6   2    9   8
+             269 26  269 8    4   7   5    3   1
+             8   5   1   3    2   9   46   467 47
+             246 9   236 4567 678 123 348  15  247
+             7   8   23  45   9   123 34   15  6
+             246 1   5   467  678 23  3489 47  2479
+            ",
+        );
+        assert_finds(&puzzle, TechniqueKind::UniqueRectangle6, "r3c2<>3, r1c5<>3");
+    }
+
+    #[test]
+    fn hidden_rectangle() {
+        let puzzle = parse_candidates(
+            "
+             1   5  8 3   6   4   7    2   9
+             7   3  4 29  29  1   68   68  5
+             2   6  9 57  578 58  4    3   1
+             3   48 6 1   245 7   9    458 28
+             49  7  2 8   345 59  1    456 36
+             489 1  5 6   234 29  238  48  7
+             458 48 7 245 1   3   2568 9   2468
+             6   9  1 245 258 258 235  7   34
+             458 2  3 79  79  6   58   1   48
+            ",
+        );
+        assert_finds(&puzzle, TechniqueKind::HiddenRectangle, "r8c5<>5");
+    }
+
+    #[test]
+    fn bug_plus_one() {
+        let puzzle = parse_candidates(
+            "
+             6  4   58 3 9 2 1  7 58
+             7  59  3  8 1 4 2  6 59
+             29 28  1  5 7 6 89 3 4
+             1  7   4  9 6 8 5  2 3
+             35 35  9  4 2 7 6  8 1
+             8  6   2  1 5 3 4  9 7
+             23 1   7  6 4 9 38 5 28
+             4  23  6  7 8 5 39 1 29
+             59 589 58 2 3 1 7  4 6
+            ",
+        );
+        assert_finds(&puzzle, TechniqueKind::BugPlusOne, "r9c2=5");
+    }
+}
@@ -0,0 +1,214 @@
+use std::fmt::Display;
+
+use super::{puzzle::index, step::digits_to_string, Puzzle, PuzzleIndex, SliceType, SudokuError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rule {
+    // 確定したマスが同じ値を持つ
+    DuplicateDigit,
+    // すべてのビット和が0b111111111とならない
+    MissingDigit,
+    // 確定したマスと未確定のマスのビット和が重なる
+    CandidateConflict,
+}
+
+impl Display for Rule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::DuplicateDigit => write!(f, "duplicate digit"),
+            Self::MissingDigit => write!(f, "missing digit"),
+            Self::CandidateConflict => write!(f, "candidate conflict"),
+        }
+    }
+}
+
+// 一つの行/列/ブロックで一つの規則に反しているもの
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    slice: SliceType,
+    rule: Rule,
+    // 違反に関わるマス. CandidateConflictでは確定したマス, 候補に残っているマスの順
+    cells: Vec<PuzzleIndex>,
+    digits: u16,
+}
+
+impl Violation {
+    pub fn slice(&self) -> SliceType {
+        self.slice
+    }
+
+    pub fn rule(&self) -> Rule {
+        self.rule
+    }
+
+    pub fn cells(&self) -> &[PuzzleIndex] {
+        &self.cells
+    }
+
+    pub fn digits(&self) -> u16 {
+        self.digits
+    }
+}
+
+impl Display for Violation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: {} {{{}}}",
+            self.slice,
+            self.rule,
+            digits_to_string(self.digits)
+        )?;
+        for pos in &self.cells {
+            write!(f, " {}", pos)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ValidationReport {
+    // 未確定なのに候補がないマス
+    empty_cells: Vec<PuzzleIndex>,
+    violations: Vec<Violation>,
+}
+
+impl ValidationReport {
+    pub fn is_valid(&self) -> bool {
+        self.empty_cells.is_empty() && self.violations.is_empty()
+    }
+
+    pub fn empty_cells(&self) -> &[PuzzleIndex] {
+        &self.empty_cells
+    }
+
+    pub fn violations(&self) -> &[Violation] {
+        &self.violations
+    }
+}
+
+impl Puzzle {
+    // 候補のないマスと, 各行/列/ブロックで
+    // - 確定したマスが同じ値を持つ
+    // - どこのマスにも入らない数字がある (ビット和が0b111111111とならない)
+    // - 確定した数字が未確定のマスの候補に残っている
+    // ものをすべて集める.
+    pub fn validation_report(&self) -> ValidationReport {
+        let mut report = ValidationReport::default();
+        for i in 0..81 {
+            let cell = &self[index(i)];
+            if !cell.is_filled() && cell.bit() == 0 {
+                report.empty_cells.push(cell.pos());
+            }
+        }
+        let slices = (0..9)
+            .map(SliceType::Block)
+            .chain((0..9).map(SliceType::Col))
+            .chain((0..9).map(SliceType::Row));
+        for type_ in slices {
+            let s = self.slice(type_);
+            let (filled, unfilled): (Vec<_>, Vec<_>) = s.iter().partition(|c| c.is_filled());
+            let twice = (0..9)
+                .map(|k| 1 << k)
+                .filter(|&bit| filled.iter().filter(|c| c.bit() == bit).count() > 1)
+                .fold(0, |acc, bit| acc | bit);
+            let filled_bits = filled.iter().fold(0, |acc, c| acc | c.bit());
+            let unfilled_bits = unfilled.iter().fold(0, |acc, c| acc | c.bit());
+            let missing = 0b111111111 & !(filled_bits | unfilled_bits);
+            let overlap = filled_bits & unfilled_bits;
+            let mut push = |rule, digits: u16, cells: Vec<PuzzleIndex>| {
+                if digits != 0 {
+                    report.violations.push(Violation {
+                        slice: type_,
+                        rule,
+                        cells,
+                        digits,
+                    });
+                }
+            };
+            let duplicated = filled
+                .iter()
+                .filter(|c| c.bit() & twice != 0)
+                .map(|c| c.pos())
+                .collect();
+            push(Rule::DuplicateDigit, twice, duplicated);
+            push(Rule::MissingDigit, missing, vec![]);
+            let conflicting = filled
+                .iter()
+                .chain(&unfilled)
+                .filter(|c| c.bit() & overlap != 0)
+                .map(|c| c.pos())
+                .collect();
+            push(Rule::CandidateConflict, overlap, conflicting);
+        }
+        report
+    }
+
+    // validation_reportで最初に見つかった違反を返す. 一つの行/列/ブロックで複数の数字が違反していれば小さい数字を返す.
+    pub fn validate(&self) -> Result<(), SudokuError> {
+        let report = self.validation_report();
+        if let Some(&pos) = report.empty_cells.first() {
+            return Err(SudokuError::NoCandidates(pos));
+        }
+        let Some(violation) = report.violations.first() else {
+            return Ok(());
+        };
+        let bit = violation.digits & violation.digits.wrapping_neg();
+        let slice = violation.slice;
+        let digit = bit.trailing_zeros() as u8 + 1;
+        let cells = violation
+            .cells
+            .iter()
+            .copied()
+            .filter(|p| self[*p].bit() & bit != 0)
+            .collect();
+        Err(match violation.rule {
+            Rule::DuplicateDigit => SudokuError::DuplicateDigit {
+                slice,
+                digit,
+                cells,
+            },
+            Rule::MissingDigit => SudokuError::MissingDigit { slice, digit },
+            Rule::CandidateConflict => SudokuError::CandidateConflict {
+                slice,
+                digit,
+                cells,
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn report_lists_every_duplicate() {
+        // fillは重複を入れないので, 候補を直接書き換える
+        let mut puzzle = Puzzle::new();
+        for (i, v) in [(0, 1), (1, 1), (4, 3), (5, 3), (40, 2), (76, 2)] {
+            puzzle[index(i)].insert(v);
+        }
+        let report = puzzle.validation_report();
+        assert!(!report.is_valid());
+        let duplicates = report
+            .violations()
+            .iter()
+            .filter(|v| v.rule() == Rule::DuplicateDigit)
+            .map(|v| v.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            duplicates,
+            [
+                "b1: duplicate digit {1} r1c1 r1c2",
+                "b2: duplicate digit {3} r1c5 r1c6",
+                "r1: duplicate digit {13} r1c1 r1c2 r1c5 r1c6",
+                "c5: duplicate digit {2} r5c5 r9c5",
+            ]
+        );
+        assert!(matches!(
+            puzzle.validate(),
+            Err(SudokuError::DuplicateDigit { digit: 1, .. })
+        ));
+    }
+}
@@ -0,0 +1,226 @@
+use crate::cell::Cell;
+
+use super::{puzzle::combinations, step::TechniqueKind, Puzzle, PuzzleIndex, Step};
+
+impl Puzzle {
+    // idxと同じ行/列/ブロックにある未確定のマスのうちacceptを満たすもの
+    fn wing_candidates(
+        &self,
+        idx: PuzzleIndex,
+        accept: impl Fn(&Cell) -> bool,
+    ) -> Vec<PuzzleIndex> {
+        let mut candidates = vec![];
+        for tar in [
+            self.row(idx.row()),
+            self.col(idx.col()),
+            self.block(idx.block_idx()),
+        ] {
+            for i in 0..9 {
+                if tar[i].is_filled() || tar[i].pos() == idx || !accept(&tar[i]) {
+                    continue;
+                }
+                if !candidates.contains(&tar[i].pos()) {
+                    candidates.push(tar[i].pos());
+                }
+            }
+        }
+        candidates
+    }
+
+    pub(super) fn xy_wing(&self) -> Vec<Step> {
+        let mut ret = vec![];
+        for i in 0..9 {
+            for j in 0..9 {
+                let idx = PuzzleIndex::new(i, j);
+                if self[idx].is_filled() || self[idx].bit().count_ones() != 2 {
+                    continue;
+                }
+                // 立っているビットの数が2かつself[idx]と一つだけ立っているビットが共通しているセルの集合
+                let candidates = self.wing_candidates(idx, |c| {
+                    c.bit().count_ones() == 2 && (c.bit() & self[idx].bit()).count_ones() == 1
+                });
+                fn is_same_group(i: &Cell, j: &Cell, k: &Cell) -> bool {
+                    (i.row() == j.row() && i.row() == k.row())
+                        || (i.col() == j.col() && i.col() == k.col())
+                        || (i.block_idx() == j.block_idx() && i.block_idx() == k.block_idx())
+                }
+                for i in 0..candidates.len() {
+                    for j in i + 1..candidates.len() {
+                        let i = candidates[i];
+                        let j = candidates[j];
+                        // 三つのセルの論理和の立っているビットが3つで, self[idx]は二つのセルと異なる共通セルを持つ.
+                        if is_same_group(&self[idx], &self[i], &self[j])
+                            || (self[i].bit() | self[j].bit() | self[idx].bit()).count_ones() != 3
+                            || (self[i].bit() & self[idx].bit())
+                                == (self[j].bit() & self[idx].bit())
+                        {
+                            continue;
+                        }
+                        let common = self[i].bit() & self[j].bit();
+                        let step = self.wing_step(
+                            TechniqueKind::XYWing,
+                            vec![idx, i, j],
+                            self[i].bit() | self[j].bit(),
+                            common,
+                        );
+                        ret.push(step);
+                    }
+                }
+            }
+        }
+        ret
+    }
+
+    pub(super) fn xyz_wing(&self) -> Vec<Step> {
+        // 候補が3つのマスと, そのうち二つずつを候補に持つ二つのマスで, 三つすべてに共通する数字をすべてが見えるマスから取り除く.
+        let mut ret = vec![];
+        for i in 0..9 {
+            for j in 0..9 {
+                let idx = PuzzleIndex::new(i, j);
+                let pivot = self[idx].bit();
+                if self[idx].is_filled() || pivot.count_ones() != 3 {
+                    continue;
+                }
+                let candidates = self.wing_candidates(idx, |c| {
+                    c.bit().count_ones() == 2 && c.bit() & pivot == c.bit()
+                });
+                for wings in combinations(&candidates, 2) {
+                    let (i, j) = (wings[0], wings[1]);
+                    if self[i].bit() == self[j].bit() {
+                        continue;
+                    }
+                    let common = self[i].bit() & self[j].bit();
+                    ret.push(self.wing_step(
+                        TechniqueKind::XYZWing,
+                        vec![idx, i, j],
+                        pivot,
+                        common,
+                    ));
+                }
+            }
+        }
+        ret
+    }
+
+    pub(super) fn wxyz_wing(&self) -> Vec<Step> {
+        // 4つのマスの候補の和が4つの数字で, 互いに見えないマスにまたがる数字が一つだけならば, その数字を持つマスすべてが見えるマスから取り除く.
+        let mut ret = vec![];
+        for i in 0..9 {
+            for j in 0..9 {
+                let idx = PuzzleIndex::new(i, j);
+                if self[idx].is_filled() || self[idx].bit().count_ones() > 4 {
+                    continue;
+                }
+                let candidates =
+                    self.wing_candidates(idx, |c| (2..=4).contains(&c.bit().count_ones()));
+                for wings in combinations(&candidates, 3) {
+                    let cells = [idx, wings[0], wings[1], wings[2]];
+                    let digits = cells.iter().fold(0, |acc, p| acc | self[*p].bit());
+                    if digits.count_ones() != 4 {
+                        continue;
+                    }
+                    let unrestricted = (0..9)
+                        .map(|k| 1u16 << k)
+                        .filter(|&b| {
+                            let with = cells.iter().filter(|p| self[**p].bit() & b != 0);
+                            let with = with.collect::<Vec<_>>();
+                            combinations(&with, 2).iter().any(|c| !c[0].is_peer(c[1]))
+                        })
+                        .collect::<Vec<_>>();
+                    if unrestricted.len() != 1 {
+                        continue;
+                    }
+                    ret.push(self.wing_step(
+                        TechniqueKind::WXYZWing,
+                        cells.to_vec(),
+                        digits,
+                        unrestricted[0],
+                    ));
+                }
+            }
+        }
+        ret
+    }
+
+    // cellsのうちzを持つマスすべてが見えるマスからzを取り除く手順
+    fn wing_step(
+        &self,
+        technique: TechniqueKind,
+        cells: Vec<PuzzleIndex>,
+        digits: u16,
+        z: u16,
+    ) -> Step {
+        let with_z = cells
+            .iter()
+            .filter(|p| self[**p].bit() & z != 0)
+            .copied()
+            .collect::<Vec<_>>();
+        let mut step = Step::new(technique, cells, digits);
+        for k in self.peers(with_z[0]) {
+            if with_z.iter().all(|p| k.is_peer(p)) {
+                step.eliminate(&self[k], z);
+            }
+        }
+        step
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::puzzle::{assert_finds, parse_candidates};
+    use super::*;
+
+    #[test]
+    fn xywing() {
+        let puzzle = parse_candidates(
+            "
+             1   5  8 3   6   4   7  2   9
+             7   3  4 29  29  1   68 68  5
+             2   6  9 57  578 58  4  3   1
+             3   48 6 1   45  7   9  458 2
+             49  7  2 8   345 59  1  456 36
+             489 1  5 6   234 29  38 48  7
+             5   48 7 24  1   3   26 9   468
+             6   9  1 245 28  258 23 7   34
+             48  2  3 79  79  6   5  1   48
+            ",
+        );
+        assert_finds(&puzzle, TechniqueKind::XYWing, "r4c2<>4, r5c5<>4");
+    }
+
+    #[test]
+    fn xyzwing() {
+        let puzzle = parse_candidates(
+            "
+             4    2    9    38   18   7     5  138  6
+             8    7    35   235  1245 6     14 134  9
+             356  1    356  9    458  3458  2  7    48
+             59   48   2    18   6    148   7  59   3
+             7    3459 1    235  245  3459  8  6    24
+             359  6    3458 7    2458 34589 14 59   124
+             156  458  7    1568 9    2     3  148  148
+             1236 348  3468 168  7    18    9  1248 5
+             1259 589  58   4    3    158   6  128  7
+            ",
+        );
+        assert_finds(&puzzle, TechniqueKind::XYZWing, "r8c2<>8, r9c2<>8");
+    }
+
+    #[test]
+    fn wxyzwing() {
+        let puzzle = parse_candidates(
+            "
+             4    8  29   1256  3    125  69 7 12
+             239  1  2379 8     246  27   69 5 24
+             6    27 5    124   9    127  8  3 124
+             123  47 47   1235  125  6    23 8 9
+             1238 9  238  123   7    1238 4  6 5
+             238  5  6    9     28   4    23 1 7
+             5    3  1489 14    148  189  7  2 6
+             7    24 124  12346 1246 123  5  9 8
+             289  6  289  7     258  2589 1  4 3
+            ",
+        );
+        assert_finds(&puzzle, TechniqueKind::WXYZWing, "r1c4<>1");
+    }
+}
@@ -0,0 +1,233 @@
+use std::{collections::BTreeMap, fmt::Display};
+
+use crate::{
+    puzzle::{PuzzleIndex, Solver, SudokuError, TechniqueKind},
+    Puzzle,
+};
+
+// HoDoKuの難易度に倣った段階. Expertは HoDoKu の Unfair と Extreme をまとめたもの
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Tier {
+    Easy,
+    Medium,
+    Hard,
+    Expert,
+    BeyondSolver,
+}
+
+impl Tier {
+    // 合計点から決まる段階
+    fn from_score(score: u32) -> Self {
+        match score {
+            0..=800 => Self::Easy,
+            801..=1000 => Self::Medium,
+            1001..=1600 => Self::Hard,
+            _ => Self::Expert,
+        }
+    }
+}
+
+impl Display for Tier {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::Easy => "Easy",
+            Self::Medium => "Medium",
+            Self::Hard => "Hard",
+            Self::Expert => "Expert",
+            Self::BeyondSolver => "Beyond Solver",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+// 解法一回あたりの点数と, その解法が必要なときの最低限の段階 (HoDoKuの既定値)
+fn weight(technique: TechniqueKind) -> (u32, Tier) {
+    match technique {
+        TechniqueKind::NakedSingle => (4, Tier::Easy),
+        TechniqueKind::HiddenSingle => (14, Tier::Easy),
+        TechniqueKind::Pointing => (50, Tier::Medium),
+        TechniqueKind::Claiming => (50, Tier::Medium),
+        TechniqueKind::NakedPair => (60, Tier::Medium),
+        TechniqueKind::HiddenPair => (70, Tier::Medium),
+        TechniqueKind::NakedTriple => (80, Tier::Medium),
+        TechniqueKind::HiddenTriple => (100, Tier::Medium),
+        TechniqueKind::XWing => (140, Tier::Hard),
+        TechniqueKind::Swordfish => (150, Tier::Hard),
+        TechniqueKind::Skyscraper => (130, Tier::Hard),
+        TechniqueKind::TwoStringKite => (150, Tier::Hard),
+        TechniqueKind::EmptyRectangle => (120, Tier::Hard),
+        TechniqueKind::TurbotFish => (120, Tier::Hard),
+        TechniqueKind::XYWing => (160, Tier::Hard),
+        TechniqueKind::XYZWing => (180, Tier::Hard),
+        TechniqueKind::UniqueRectangle1 => (100, Tier::Hard),
+        TechniqueKind::UniqueRectangle2 => (100, Tier::Hard),
+        TechniqueKind::UniqueRectangle3 => (100, Tier::Hard),
+        TechniqueKind::UniqueRectangle4 => (100, Tier::Hard),
+        TechniqueKind::UniqueRectangle5 => (100, Tier::Hard),
+        TechniqueKind::UniqueRectangle6 => (100, Tier::Hard),
+        TechniqueKind::HiddenRectangle => (100, Tier::Hard),
+        TechniqueKind::BugPlusOne => (100, Tier::Hard),
+        TechniqueKind::NakedQuad => (120, Tier::Hard),
+        TechniqueKind::Jellyfish => (160, Tier::Hard),
+        TechniqueKind::HiddenQuad => (150, Tier::Hard),
+        TechniqueKind::ColorTrap => (150, Tier::Hard),
+        TechniqueKind::ColorWrap => (150, Tier::Hard),
+        TechniqueKind::MultiColoring => (200, Tier::Hard),
+        TechniqueKind::FinnedXWing => (130, Tier::Expert),
+        TechniqueKind::SashimiXWing => (150, Tier::Expert),
+        TechniqueKind::FinnedSwordfish => (200, Tier::Expert),
+        TechniqueKind::WXYZWing => (200, Tier::Expert),
+        TechniqueKind::SashimiSwordfish => (240, Tier::Expert),
+        TechniqueKind::FinnedJellyfish => (250, Tier::Expert),
+        TechniqueKind::SashimiJellyfish => (260, Tier::Expert),
+        TechniqueKind::SueDeCoq => (250, Tier::Expert),
+        TechniqueKind::XChain => (260, Tier::Expert),
+        TechniqueKind::XYChain => (260, Tier::Expert),
+        TechniqueKind::Medusa3D => (260, Tier::Expert),
+        TechniqueKind::XCycle => (270, Tier::Expert),
+        TechniqueKind::AIC => (280, Tier::Expert),
+        TechniqueKind::GroupedAIC => (300, Tier::Expert),
+        TechniqueKind::AlsXz => (300, Tier::Expert),
+        TechniqueKind::AlsXyWing => (320, Tier::Expert),
+        TechniqueKind::DeathBlossom => (360, Tier::Expert),
+        TechniqueKind::Nishio => (450, Tier::Expert),
+        TechniqueKind::CellForcingChain => (500, Tier::Expert),
+        TechniqueKind::UnitForcingChain => (550, Tier::Expert),
+        TechniqueKind::SkLoop => (600, Tier::Expert),
+        TechniqueKind::Msls => (650, Tier::Expert),
+        TechniqueKind::JuniorExocet => (700, Tier::Expert),
+        TechniqueKind::SeniorExocet => (750, Tier::Expert),
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Rating {
+    score: u32,
+    tier: Tier,
+    hardest: Option<TechniqueKind>,
+    techniques: BTreeMap<TechniqueKind, usize>,
+}
+
+impl Rating {
+    pub fn score(&self) -> u32 {
+        self.score
+    }
+
+    pub fn tier(&self) -> Tier {
+        self.tier
+    }
+
+    // 必要だった解法のうち最も難しいもの
+    pub fn hardest(&self) -> Option<TechniqueKind> {
+        self.hardest
+    }
+
+    // 解法ごとの使用回数
+    pub fn techniques(&self) -> &BTreeMap<TechniqueKind, usize> {
+        &self.techniques
+    }
+}
+
+// 常に最も簡単な手順を一つずつ適用して解き, 使った解法の点数を合計する. 既に確定しているマスはヒントとして扱うので,
+// fillで連鎖して埋まったマスは数えられない. ヒントだけの盤面はPuzzle::from_cluesで作る.
+// 手順を適用して矛盾が出たらエラーを返す
+pub fn rate(puzzle: &Puzzle) -> Result<Rating, SudokuError> {
+    let mut puzzle = puzzle.clone();
+    let mut score = 0;
+    let mut tier = Tier::Easy;
+    let mut techniques = BTreeMap::new();
+    // 解が一つに定まる問題ならUnique RectangleやBUG+1も使う
+    let solver = Solver::new()
+        .techniques(&TechniqueKind::ALL)
+        .assume_unique(puzzle.is_unique());
+    while let Some(step) = solver.next_hint(&puzzle) {
+        let (s, t) = weight(step.technique());
+        score += s;
+        tier = tier.max(t);
+        *techniques.entry(step.technique()).or_insert(0) += 1;
+        let before = filled(&puzzle);
+        puzzle.apply(&step)?;
+        // 置いた数字から連鎖して確定したマスはNaked Singleとして数える
+        let cascaded = (filled(&puzzle) - before).saturating_sub(step.placements().len());
+        if cascaded > 0 {
+            score += weight(TechniqueKind::NakedSingle).0 * cascaded as u32;
+            *techniques.entry(TechniqueKind::NakedSingle).or_insert(0) += cascaded;
+        }
+    }
+    let tier = if puzzle.is_solved() {
+        tier.max(Tier::from_score(score))
+    } else {
+        Tier::BeyondSolver
+    };
+    Ok(Rating {
+        score,
+        tier,
+        hardest: techniques.keys().next_back().copied(),
+        techniques,
+    })
+}
+
+// 確定したマスの数
+fn filled(puzzle: &Puzzle) -> usize {
+    (0..81)
+        .filter(|&i| puzzle[PuzzleIndex::new(i / 9, i % 9)].is_filled())
+        .count()
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 画面の左上から行ごとに並べたヒント. 数字以外は空きマス
+    fn clues(s: &str) -> [u8; 81] {
+        let mut clues = [0; 81];
+        for (v, c) in clues.iter_mut().zip(s.chars()) {
+            *v = c.to_digit(10).unwrap_or(0) as u8;
+        }
+        clues
+    }
+
+    #[test]
+    fn naked_singles_are_counted() {
+        // ヒント30個から, 残りの51マスがNaked Singleだけで埋まる
+        let puzzle = Puzzle::from_clues(&clues(
+            "53..7....6..195....98....6.8...6...34..8.3..17...2...6.6....28....419..5....8..79",
+        ));
+        let rating = rate(&puzzle).unwrap();
+        assert_eq!(rating.score(), 51 * 4);
+        assert_eq!(rating.tier(), Tier::Easy);
+        assert_eq!(rating.hardest(), Some(TechniqueKind::NakedSingle));
+        assert_eq!(rating.techniques()[&TechniqueKind::NakedSingle], 51);
+    }
+
+    #[test]
+    fn tier_is_at_least_that_of_the_hardest_technique() {
+        // 点数ではEasyだが, Pointingが必要なのでMedium
+        let puzzle = Puzzle::from_clues(&clues(
+            "*****82******7***5*1****8***7******66891**3****3**9***56**8*1****1*4******2**19**",
+        ));
+        let rating = rate(&puzzle).unwrap();
+        assert_eq!(rating.score(), 458);
+        assert_eq!(rating.tier(), Tier::Medium);
+        assert_eq!(rating.hardest(), Some(TechniqueKind::Pointing));
+
+        let puzzle = Puzzle::from_clues(&clues(
+            "62*******49**2***8***9*8***8****61********94*3****48*2*8*5**4***5*3*7********1*5*",
+        ));
+        let rating = rate(&puzzle).unwrap();
+        assert_eq!(rating.score(), 1154);
+        assert_eq!(rating.tier(), Tier::Hard);
+        assert_eq!(rating.hardest(), Some(TechniqueKind::XYWing));
+    }
+
+    #[test]
+    fn unsolved_puzzle_is_beyond_solver() {
+        // 解からr2c8, r2c9, r7c8, r7c9 (4と8の長方形) を抜いたもの. 解が二つあり, どの解法でも進まない
+        let puzzle = Puzzle::from_clues(&clues(
+            "5346789126721953**1983425678597614234268537917139248569615372**287419635345286179",
+        ));
+        let rating = rate(&puzzle).unwrap();
+        assert_eq!(rating.score(), 0);
+        assert_eq!(rating.tier(), Tier::BeyondSolver);
+        assert_eq!(rating.hardest(), None);
+    }
+}